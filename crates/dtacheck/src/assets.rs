@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+use arson_parse::reporting as codespan_reporting;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Label;
+
+use crate::linter::Lint;
+use crate::preprocess::PLATFORMS;
+
+/// Where files that scripts refer to by path are looked for: next to the
+/// file being linted, then under the game's root.
+#[derive(Clone, Copy)]
+pub struct Assets<'a> {
+    pub root: &'a Path,
+    pub dir: Option<&'a Path>,
+    // where each file was found, or where it was expected when it wasn't,
+    // for the depfile
+    pub probed: &'a RefCell<BTreeSet<PathBuf>>,
+}
+
+impl Assets<'_> {
+    fn bases(&self, path: &str) -> Vec<PathBuf> {
+        let mut bases = Vec::new();
+        if let Some(dir) = self.dir {
+            bases.push(dir.join(path));
+        }
+        bases.push(self.root.join(path));
+        bases
+    }
+
+    // the build puts converted files in a `gen` directory next to the
+    // source, with the platform appended to the extension, so
+    // `ui/image.png` may only exist as `ui/gen/image.png_xbox`
+    fn candidates(&self, path: &str) -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+        for base in self.bases(path) {
+            let (Some(parent), Some(file_name)) =
+                (base.parent(), base.file_name())
+            else {
+                continue;
+            };
+            let generated = parent.join("gen").join(file_name);
+            for path in [base.clone(), generated] {
+                for (platform, _) in PLATFORMS {
+                    let mut name = path.clone().into_os_string();
+                    name.push(format!("_{platform}"));
+                    candidates.push(PathBuf::from(name));
+                }
+                candidates.push(path);
+            }
+        }
+        candidates
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        let found = self.candidates(path).into_iter().find(|p| p.is_file());
+        let exists = found.is_some();
+        let probed = found.unwrap_or_else(|| self.bases(path).swap_remove(0));
+        self.probed.borrow_mut().insert(probed);
+        exists
+    }
+}
+
+pub struct MissingAsset {
+    pub path: String,
+    pub range: Range<usize>,
+}
+
+impl Lint for MissingAsset {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        Diagnostic::warning()
+            .with_message(format!("could not find `{}`", self.path))
+            .with_labels(vec![Label::primary(id, self.range.clone())
+                .with_message("file not found")])
+            .with_notes(vec![
+                "also looked in `gen` with each platform's suffix".to_string(),
+            ])
+    }
+
+    fn id(&self) -> &'static str {
+        "missing-asset"
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use arson_parse::reporting as codespan_reporting;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::LabelStyle;
+use codespan_reporting::files::Files;
+use serde_json::json;
+use serde_json::Value;
+
+// lint id, file name and fingerprint
+type Key = (String, String, String);
+
+// 64-bit FNV-1a, which unlike the standard library's hasher is the same
+// on every platform and toolchain
+fn fnv1a(text: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// identifies a diagnostic by its message and the text of the line it
+// points at, so it survives edits elsewhere in the file
+fn key<'a, F: Files<'a, FileId = usize>>(
+    files: &'a F,
+    diag: &Diagnostic<usize>,
+) -> Option<Key> {
+    let id = diag.code.clone()?;
+    let label = diag
+        .labels
+        .iter()
+        .find(|label| label.style == LabelStyle::Primary)?;
+    let name = files.name(label.file_id).ok()?.to_string();
+    let source = files.source(label.file_id).ok()?;
+    let line = files.line_index(label.file_id, label.range.start).ok()?;
+    let line_range = files.line_range(label.file_id, line).ok()?;
+    let line_text = source.as_ref().get(line_range).unwrap_or_default();
+
+    let fingerprint = fnv1a(&format!("{}\n{}", diag.message, line_text.trim()));
+    Some((id, name, format!("{fingerprint:016x}")))
+}
+
+/// Diagnostics that were already present when a baseline was written, so
+/// that only new ones are reported. Diagnostics without a lint id, such as
+/// config errors, are never part of a baseline.
+#[derive(Default)]
+pub struct Baseline {
+    // how many times each diagnostic was seen
+    entries: BTreeMap<Key, usize>,
+}
+
+impl Baseline {
+    pub fn from_diagnostics<'a, F: Files<'a, FileId = usize>>(
+        files: &'a F,
+        diagnostics: &[Diagnostic<usize>],
+    ) -> Self {
+        let mut baseline = Self::default();
+        for diag in diagnostics {
+            if let Some(key) = key(files, diag) {
+                *baseline.entries.entry(key).or_default() += 1;
+            }
+        }
+        baseline
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let value =
+            serde_json::from_str::<Value>(text).map_err(|e| e.to_string())?;
+        let entries = value.as_array().ok_or("expected an array")?;
+
+        let mut baseline = Self::default();
+        for entry in entries {
+            let field = |name: &str| {
+                entry
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .ok_or(format!("expected a `{name}` string in {entry}"))
+            };
+            let key = (field("lint")?, field("file")?, field("fingerprint")?);
+            let count = entry.get("count").and_then(Value::as_u64).unwrap_or(1);
+            *baseline.entries.entry(key).or_default() += count as usize;
+        }
+        Ok(baseline)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let entries = self
+            .entries
+            .iter()
+            .map(|((lint, file, fingerprint), count)| {
+                json!({
+                    "lint": lint,
+                    "file": file,
+                    "fingerprint": fingerprint,
+                    "count": count,
+                })
+            })
+            .collect::<Vec<_>>();
+        Value::Array(entries)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops diagnostics in the baseline. A diagnostic seen more times
+    /// than the baseline recorded is still reported for the extras.
+    pub fn filter<'a, F: Files<'a, FileId = usize>>(
+        &self,
+        files: &'a F,
+        diagnostics: Vec<Diagnostic<usize>>,
+    ) -> Vec<Diagnostic<usize>> {
+        let mut remaining = self.entries.clone();
+        diagnostics
+            .into_iter()
+            .filter(|diag| {
+                let Some(count) =
+                    key(files, diag).and_then(|key| remaining.get_mut(&key))
+                else {
+                    return true;
+                };
+                if *count == 0 {
+                    return true;
+                }
+                *count -= 1;
+                false
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Label;
+    use codespan_reporting::files::SimpleFiles;
+
+    use super::*;
+
+    fn lint(file: usize, start: usize) -> Diagnostic<usize> {
+        Diagnostic::warning()
+            .with_message("calling `a` with too many arguments")
+            .with_code("too-many-args")
+            .with_labels(vec![Label::primary(file, start..start + 1)])
+    }
+
+    #[test]
+    fn filters_only_what_the_baseline_recorded() {
+        let text = "{a 1 2}\n{a 1 2}\n{a 3 4}\n";
+        let mut files = SimpleFiles::new();
+        let file = files.add("a.dta".to_string(), text.to_string());
+
+        let baseline = Baseline::from_diagnostics(
+            &files,
+            &[lint(file, 0), lint(file, 16)],
+        );
+        assert_eq!(baseline.len(), 2);
+
+        // the first line's diagnostic is now on the identical second line
+        // too, but was only recorded once
+        let diagnostics = vec![lint(file, 0), lint(file, 8), lint(file, 16)];
+        let remaining = baseline.filter(&files, diagnostics);
+        assert_eq!(remaining.len(), 1);
+    }
+
+    #[test]
+    fn matches_after_edits_elsewhere() {
+        let before = "{a 1 2}\n";
+        let after = "; a new comment\n\n{a 1 2}\n";
+        let mut files = SimpleFiles::new();
+        let old = files.add("a.dta".to_string(), before.to_string());
+        let baseline = Baseline::from_diagnostics(&files, &[lint(old, 0)]);
+
+        let mut files = SimpleFiles::new();
+        let new = files.add("a.dta".to_string(), after.to_string());
+        let remaining = baseline.filter(&files, vec![lint(new, 17)]);
+        assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn only_lints_are_recorded() {
+        let text = "{a 1 2}\n";
+        let mut files = SimpleFiles::new();
+        let file = files.add("a.dta".to_string(), text.to_string());
+        let config_error = Diagnostic::error()
+            .with_message("conflicting signatures for `a`")
+            .with_labels(vec![Label::primary(file, 0..1)]);
+
+        let baseline = Baseline::from_diagnostics(
+            &files,
+            std::slice::from_ref(&config_error),
+        );
+        assert!(baseline.is_empty());
+        assert_eq!(baseline.filter(&files, vec![config_error]).len(), 1);
+    }
+
+    #[test]
+    fn counts_survive_a_round_trip() {
+        let text = "{a 1 2}\n{a 1 2}\n";
+        let mut files = SimpleFiles::new();
+        let file = files.add("a.dta".to_string(), text.to_string());
+        let diagnostics = vec![lint(file, 0), lint(file, 8)];
+        let baseline = Baseline::from_diagnostics(&files, &diagnostics);
+
+        let json = baseline.to_json();
+        assert_eq!(json[0]["count"], 2);
+        let parsed = Baseline::parse(&json.to_string()).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed.filter(&files, diagnostics).is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        assert!(Baseline::parse("{}").is_err());
+        assert!(Baseline::parse(r#"[{"lint": "too-many-args"}]"#).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Label;
+
+use crate::linter::Function;
+use crate::linter::Lint;
+
+struct UnknownFunction {
+    name: String,
+    range: Range<usize>,
+    suggestion: Option<String>,
+}
+
+impl Lint for UnknownFunction {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        let diag = Diagnostic::warning()
+            .with_message(format!("unknown function `{}`", self.name))
+            .with_labels(vec![Label::primary(id, self.range.clone())]);
+        match &self.suggestion {
+            Some(suggestion) => {
+                diag.with_notes(vec![format!("did you mean `{suggestion}`?")])
+            }
+            None => diag,
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        "unknown-function"
+    }
+}
+
+struct Call {
+    name: String,
+    file_id: usize,
+    range: Range<usize>,
+}
+
+fn symbol_name<'a>(node: &'a Expression) -> Option<&'a str> {
+    match &node.value {
+        ExpressionValue::Symbol(sym) => Some(sym.as_ref()),
+        _ => None,
+    }
+}
+
+// handlers are written `(name {body} ...)` or `(name ($args) {body} ...)`
+pub fn handler_name<'a>(array: &'a [Expression]) -> Option<&'a str> {
+    let [head, body, ..] = array else {
+        return None;
+    };
+    let is_handler = match &body.value {
+        ExpressionValue::Command(_) => true,
+        ExpressionValue::Array(args) => args
+            .iter()
+            .all(|arg| matches!(arg.value, ExpressionValue::Variable(_))),
+        _ => false,
+    };
+    is_handler.then(|| symbol_name(head)).flatten()
+}
+
+/// Command heads and the functions, handlers and objects defined in script
+/// across the files being linted.
+#[derive(Default)]
+pub struct CallTable {
+    calls: Vec<Call>,
+    defined: HashSet<String>,
+}
+
+impl CallTable {
+    pub fn add_file(&mut self, file_id: usize, ast: &[Expression]) {
+        for node in ast {
+            match &node.value {
+                ExpressionValue::Command(array) => {
+                    if let Some(name) = array.first().and_then(symbol_name) {
+                        self.calls.push(Call {
+                            name: name.to_string(),
+                            file_id,
+                            range: array[0].location.clone(),
+                        });
+                    }
+                    // {func name ($args) body...}
+                    if let [head, name, ..] = array.as_slice() {
+                        if symbol_name(head) == Some("func") {
+                            if let Some(name) = symbol_name(name) {
+                                self.defined.insert(name.to_string());
+                            }
+                        }
+                    }
+                    // {new Class name ...} makes an object that can be sent
+                    // messages as `{name handler ...}`
+                    if let [head, _, name, ..] = array.as_slice() {
+                        if symbol_name(head) == Some("new") {
+                            if let Some(name) = symbol_name(name) {
+                                self.defined.insert(name.to_string());
+                            }
+                        }
+                    }
+                    self.add_file(file_id, array);
+                }
+                ExpressionValue::Array(array) => {
+                    if let Some(name) = handler_name(array) {
+                        self.defined.insert(name.to_string());
+                    }
+                    self.add_file(file_id, array);
+                }
+                ExpressionValue::Property(array) => {
+                    self.add_file(file_id, array)
+                }
+                ExpressionValue::Define(name, array) => {
+                    // macros can stand in for a function name
+                    self.defined.insert(name.text.to_string());
+                    self.add_file(file_id, &array.exprs);
+                }
+                ExpressionValue::Autorun(array) => {
+                    self.add_file(file_id, &array.exprs)
+                }
+                ExpressionValue::Conditional {
+                    true_branch,
+                    false_branch,
+                    ..
+                } => {
+                    self.add_file(file_id, &true_branch.exprs);
+                    if let Some(false_branch) = false_branch {
+                        self.add_file(file_id, &false_branch.exprs);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Lints for calls to functions that are neither in the config nor
+    /// defined in script, with the file id each applies to. `external`
+    /// names macros defined outside the linted files, and `objects` those
+    /// declared with `#object`.
+    pub fn lints(
+        &self,
+        funcs: &Function,
+        external: &HashSet<String>,
+        objects: &HashMap<String, String>,
+    ) -> Vec<(usize, Box<dyn Lint>)> {
+        let known = funcs
+            .children
+            .keys()
+            .chain(&self.defined)
+            .chain(external)
+            .chain(objects.keys())
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>();
+
+        let mut lints: Vec<(usize, Box<dyn Lint>)> = Vec::new();
+        for call in &self.calls {
+            if known.contains(call.name.as_str()) {
+                continue;
+            }
+
+            let suggestion = known
+                .iter()
+                .map(|name| {
+                    (
+                        strsim::normalized_damerau_levenshtein(
+                            &call.name, name,
+                        ),
+                        name,
+                    )
+                })
+                .filter(|(score, _)| *score >= 0.7)
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, name)| name.to_string());
+            lints.push((
+                call.file_id,
+                Box::new(UnknownFunction {
+                    name: call.name.clone(),
+                    range: call.range.clone(),
+                    suggestion,
+                }),
+            ));
+        }
+
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use arson_parse::ParseOptions;
+
+    use super::*;
+    use crate::config::Config;
+
+    // the name and suggestion of each unknown function over a set of files
+    fn unknown(config: &str, files: &[&str]) -> Vec<(String, Vec<String>)> {
+        let config = Config::parse(Path::new("fns"), config);
+        assert!(config.diagnostics.is_empty(), "config has errors");
+        let mut table = CallTable::default();
+        for (file_id, text) in files.iter().enumerate() {
+            let options = ParseOptions {
+                include_comments: false,
+            };
+            let ast = arson_parse::parse_text(text, options).unwrap();
+            table.add_file(file_id, &ast);
+        }
+        table
+            .lints(&config.funcs, &config.macros, &config.objects)
+            .into_iter()
+            .map(|(file_id, lint)| {
+                let diag = lint.to_codespan(file_id);
+                let range = diag.labels[0].range.clone();
+                (files[file_id][range].to_string(), diag.notes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn close_names_are_suggested() {
+        let config = "print : any...\nset_volume : float\n";
+        let found = unknown(config, &["{prnt 1}\n{set_volum 1.0}\n{xyz}"]);
+        assert_eq!(
+            found,
+            [
+                (
+                    "prnt".to_string(),
+                    vec!["did you mean `print`?".to_string()]
+                ),
+                (
+                    "set_volum".to_string(),
+                    vec!["did you mean `set_volume`?".to_string()]
+                ),
+                ("xyz".to_string(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn script_definitions_are_known_across_files() {
+        let files = [
+            "{func helper ($a) {print $a}}\n(on_beat {helper 1})\n",
+            "{helper 2}\n{on_beat}\n#define MACRO_FN (print)\n{MACRO_FN}\n",
+        ];
+        let config = "func : symbol array any...\nprint : any...\n";
+        assert_eq!(unknown(config, &files), []);
+    }
+
+    #[test]
+    fn objects_are_known() {
+        let config = "new : symbol symbol\n#class Mixer\n\
+                      #object main_mixer Mixer\n";
+        let files = ["{main_mixer fade}\n{new Mixer side_mixer}\n\
+                      {side_mixer fade}\n{other_mixer fade}\n"];
+        let found = unknown(config, &files);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "other_mixer");
+    }
+}
@@ -0,0 +1,457 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::Expression;
+use arson_parse::ParseOptions;
+use codespan_reporting::diagnostic;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::LabelStyle;
+use codespan_reporting::files::Files;
+use codespan_reporting::files::SimpleFiles;
+use rayon::prelude::*;
+
+use crate::assets::Assets;
+use crate::calls::CallTable;
+use crate::config::Config;
+use crate::encoding::lint_unrenderable;
+use crate::encoding::Decoded;
+use crate::encoding::EncodingLint;
+use crate::linter::lint_file;
+use crate::linter::to_diagnostics;
+use crate::linter::DuplicateKey;
+use crate::linter::Lint;
+use crate::macros::MacroTable;
+use crate::preprocess;
+use crate::preprocess::Variant;
+use crate::program::Program;
+use crate::program::ProgramFile;
+use crate::suppress::Suppressions;
+use crate::variables::VariableTable;
+
+struct ParsedFile<'a> {
+    ast: Vec<Expression<'a>>,
+    suppressions: Suppressions,
+    // problems found while parsing
+    diagnostics: Vec<Diagnostic<usize>>,
+}
+
+fn parse_file<'a>(
+    file: &'a ProgramFile,
+    file_id: usize,
+    config: &Config,
+) -> ParsedFile<'a> {
+    let text = file.text.as_str();
+    let parse_options = ParseOptions {
+        include_comments: false,
+    };
+    let (ast, parse_errors) = match arson_parse::parse_text(text, parse_options)
+    {
+        Ok(ast) => (ast, Vec::new()),
+        Err(error) => (Vec::new(), error.diagnostics),
+    };
+
+    // suppression comments are only visible when comments are kept
+    let comment_options = ParseOptions {
+        include_comments: true,
+    };
+    let (suppressions, mut lints) =
+        match arson_parse::parse_text(text, comment_options) {
+            Ok(ast) => Suppressions::from_ast(text, &ast),
+            Err(_) => (Suppressions::default(), Vec::new()),
+        };
+    lints.extend(
+        parse_errors
+            .into_iter()
+            .map(|diag| Box::new(diag) as Box<dyn Lint>),
+    );
+    if let Some(range) = &file.mixed {
+        lints.push(Box::new(EncodingLint::Mixed(file.encoding, range.clone())));
+    }
+    lint_unrenderable(&mut lints, text, &ast);
+    let diagnostics =
+        to_diagnostics(&lints, file_id, &config.levels, &suppressions);
+
+    ParsedFile {
+        ast,
+        suppressions,
+        diagnostics,
+    }
+}
+
+fn check_program(
+    program: &Program,
+    file_ids: &[usize],
+    parsed: &HashMap<usize, ParsedFile>,
+    config: &Config,
+    variants: &[Variant],
+) -> (Vec<Diagnostic<usize>>, BTreeSet<PathBuf>) {
+    let mut diagnostics = Vec::new();
+    for (index, lint) in &program.lints {
+        let file_id = file_ids[*index];
+        diagnostics.extend(to_diagnostics(
+            &[Box::new(lint.clone()) as Box<dyn Lint>],
+            file_id,
+            &config.levels,
+            &parsed[&file_id].suppressions,
+        ));
+    }
+
+    let probed = RefCell::new(BTreeSet::new());
+    let lint_asts = |asts: &[Option<&[Expression]>]| {
+        let mut diagnostics = Vec::new();
+        for ((ast, file), file_id) in
+            asts.iter().zip(&program.files).zip(file_ids)
+        {
+            if let Some(ast) = ast {
+                let assets = program.root.as_deref().map(|root| Assets {
+                    root,
+                    dir: file.path.as_deref().and_then(Path::parent),
+                    probed: &probed,
+                });
+                let lints = lint_file(
+                    ast,
+                    &config.funcs,
+                    &config.classes,
+                    &config.objects,
+                    assets,
+                );
+                diagnostics.extend(to_diagnostics(
+                    &lints,
+                    *file_id,
+                    &config.levels,
+                    &parsed[file_id].suppressions,
+                ));
+            }
+        }
+        diagnostics
+    };
+
+    let asts = file_ids
+        .iter()
+        .map(|file_id| parsed[file_id].ast.as_slice())
+        .collect::<Vec<_>>();
+    if variants.is_empty() {
+        let asts = asts.iter().map(|&ast| Some(ast)).collect::<Vec<_>>();
+        diagnostics.extend(lint_asts(&asts));
+    } else {
+        let results = variants
+            .iter()
+            .map(|variant| {
+                let resolved =
+                    preprocess::resolve(program, &asts, &variant.defines);
+                let asts = resolved
+                    .iter()
+                    .map(|ast| ast.as_deref())
+                    .collect::<Vec<_>>();
+                (variant.name.as_str(), lint_asts(&asts))
+            })
+            .collect();
+        diagnostics.extend(preprocess::merge_variants(results));
+    }
+
+    (diagnostics, probed.into_inner())
+}
+
+/// The result of checking a set of programs together.
+pub struct Report {
+    /// The config files followed by every distinct program file, indexed
+    /// by the file ids in `diagnostics`.
+    pub files: SimpleFiles<String, String>,
+    /// For each program, the file id of each of its files.
+    pub file_ids: Vec<Vec<usize>>,
+    /// Config problems, then parse errors, then lints.
+    pub diagnostics: Vec<Diagnostic<usize>>,
+    /// Files that `path` arguments were found at, or expected at when they
+    /// weren't found.
+    pub assets: BTreeSet<PathBuf>,
+}
+
+/// Lints each program, then checks macros, globals and script functions
+/// across all of them at once. `variants` lists the sets of macros to
+/// resolve conditionals with; when empty, conditionals are left
+/// unresolved.
+pub fn check(
+    config: &Config,
+    programs: &[Program],
+    variants: &[Variant],
+) -> Report {
+    let mut files = SimpleFiles::new();
+    let config_ids = config
+        .files
+        .iter()
+        .map(|file| {
+            files.add(file.path.display().to_string(), file.text.clone())
+        })
+        .collect::<Vec<_>>();
+    // files included by several programs share an id, and are only parsed
+    // once
+    let mut path_ids = HashMap::new();
+    let mut unique = Vec::new();
+    let file_ids = programs
+        .iter()
+        .map(|program| {
+            program
+                .files
+                .iter()
+                .map(|file| {
+                    let mut add = || {
+                        let file_id =
+                            files.add(file.name.clone(), file.text.clone());
+                        unique.push((file_id, file));
+                        file_id
+                    };
+                    match &file.path {
+                        Some(path) => *path_ids
+                            .entry(path.canonicalize().unwrap_or(path.clone()))
+                            .or_insert_with(add),
+                        None => add(),
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let parsed = unique
+        .par_iter()
+        .map(|&(file_id, file)| (file_id, parse_file(file, file_id, config)))
+        .collect::<HashMap<_, _>>();
+
+    let (reports, assets): (Vec<_>, Vec<_>) = programs
+        .par_iter()
+        .zip(&file_ids)
+        .map(|(program, file_ids)| {
+            check_program(program, file_ids, &parsed, config, variants)
+        })
+        .unzip();
+
+    // macros, globals and script functions are checked across every file
+    // at once
+    let mut macro_table = MacroTable::default();
+    let mut variable_table = VariableTable::default();
+    let mut call_table = CallTable::default();
+    for &(file_id, file) in &unique {
+        let ast = &parsed[&file_id].ast;
+        macro_table.add_file(file_id, &file.text, ast);
+        variable_table.add_file(file_id, ast);
+        call_table.add_file(file_id, ast);
+    }
+    let mut project_diagnostics = Vec::new();
+    let project_lints = macro_table
+        .lints(&config.macros)
+        .into_iter()
+        .chain(variable_table.lints(&config.globals))
+        .chain(call_table.lints(
+            &config.funcs,
+            &config.macros,
+            &config.objects,
+        ));
+    for (file_id, lint) in project_lints {
+        project_diagnostics.extend(to_diagnostics(
+            &[lint],
+            file_id,
+            &config.levels,
+            &parsed[&file_id].suppressions,
+        ));
+    }
+
+    let mut diagnostics = Vec::new();
+    for diag in &config.diagnostics {
+        let mut diag = diag.clone();
+        for label in &mut diag.labels {
+            label.file_id = config_ids[label.file_id];
+        }
+        diagnostics.push(diag);
+    }
+    for &(file_id, _) in &unique {
+        diagnostics.extend(parsed[&file_id].diagnostics.iter().cloned());
+    }
+    // a file included from several places is linted more than once
+    let mut seen = HashSet::new();
+    for diag in reports.into_iter().flatten().chain(project_diagnostics) {
+        if seen.insert(DuplicateKey::new(&diag)) {
+            diagnostics.push(diag);
+        }
+    }
+
+    Report {
+        files,
+        file_ids,
+        diagnostics,
+        assets: assets.into_iter().flatten().collect(),
+    }
+}
+
+/// How serious a [`Finding`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A problem found by [`check_text`], without anything tied to how it is
+/// rendered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// Name of the file the problem is in, which may be a config file.
+    pub file: String,
+    /// Byte range of the problem in that file.
+    pub range: Range<usize>,
+    /// The lint that found it, or `None` for problems such as config errors
+    /// that aren't lints.
+    pub lint: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+    /// Further context, then where each secondary location is and what it
+    /// says, as `file:line:column: message`.
+    pub notes: Vec<String>,
+}
+
+impl Finding {
+    fn new(
+        files: &SimpleFiles<String, String>,
+        diag: &Diagnostic<usize>,
+    ) -> Self {
+        let label = diag
+            .labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary)
+            .or(diag.labels.first());
+        let file = label
+            .and_then(|label| files.get(label.file_id).ok())
+            .map(|file| file.name().clone())
+            .unwrap_or_default();
+        let severity = match diag.severity {
+            diagnostic::Severity::Bug | diagnostic::Severity::Error => {
+                Severity::Error
+            }
+            diagnostic::Severity::Warning => Severity::Warning,
+            diagnostic::Severity::Note | diagnostic::Severity::Help => {
+                Severity::Note
+            }
+        };
+
+        let secondary = diag
+            .labels
+            .iter()
+            .filter(|label| label.style == LabelStyle::Secondary)
+            .map(|label| {
+                let name = files
+                    .get(label.file_id)
+                    .map(|file| file.name().clone())
+                    .unwrap_or_default();
+                let place =
+                    match files.location(label.file_id, label.range.start) {
+                        Ok(location) => format!(
+                            "{name}:{}:{}",
+                            location.line_number, location.column_number
+                        ),
+                        Err(_) => name,
+                    };
+                if label.message.is_empty() {
+                    place
+                } else {
+                    format!("{place}: {}", label.message)
+                }
+            });
+
+        Self {
+            file,
+            range: label.map(|label| label.range.clone()).unwrap_or_default(),
+            lint: diag.code.clone(),
+            severity,
+            message: diag.message.clone(),
+            notes: diag.notes.iter().cloned().chain(secondary).collect(),
+        }
+    }
+}
+
+/// Lints DTA text held in memory on its own. Its includes are looked for
+/// under `root`, and conditionals are left unresolved.
+pub fn check_text(
+    name: &str,
+    text: &str,
+    root: Option<&Path>,
+    config: &Config,
+) -> Vec<Finding> {
+    let program = Program::load(
+        name.to_string(),
+        None,
+        Decoded::from(text.to_string()),
+        root,
+        None,
+    );
+    let report = check(config, &[program], &[]);
+    report
+        .diagnostics
+        .iter()
+        .map(|diag| Finding::new(&report.files, diag))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_text_returns_plain_findings() {
+        let config = Config::parse(
+            Path::new("fns"),
+            "play : symbol\n#overload fade : =in float\n\
+             #overload fade : =out float\n#lint unused-macro allow\n",
+        );
+        let text = "#define SONG (1)\n#define SONG (2)\n\
+                    {play SONG 2}\n{fade up 1.0}\n";
+        let findings = check_text("main.dta", text, None, &config);
+
+        let summary = findings
+            .iter()
+            .map(|finding| {
+                (
+                    finding.lint.as_deref().unwrap_or_default(),
+                    finding.severity,
+                    &text[finding.range.clone()],
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("too-many-args", Severity::Error, "{play SONG 2}"),
+                ("no-matching-overload", Severity::Error, "{fade up 1.0}"),
+                ("macro-redefined", Severity::Warning, "SONG"),
+            ]
+        );
+        assert!(findings.iter().all(|finding| finding.file == "main.dta"));
+
+        assert_eq!(
+            findings[1].notes,
+            [
+                "overload `fade : =in float`",
+                "overload `fade : =out float`"
+            ]
+        );
+        assert_eq!(findings[2].range, 25..29);
+        assert_eq!(
+            findings[2].notes,
+            ["main.dta:1:9: previously defined here"]
+        );
+    }
+
+    #[test]
+    fn config_problems_are_findings_without_a_lint() {
+        let config = Config::parse(Path::new("fns"), "play : wat\n");
+        let findings = check_text("main.dta", "(a)\n", None, &config);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].file, "fns");
+        assert_eq!(findings[0].lint, None);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].range, 7..10);
+    }
+}
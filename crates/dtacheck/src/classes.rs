@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::linter::Function;
+
+pub struct Class {
+    pub parent: Option<String>,
+    // handlers the class responds to, keyed like free functions
+    pub handlers: Function,
+    // properties its object definitions may set, with the values they take
+    pub properties: Function,
+}
+
+/// Class schemas loaded from `#class`, `#handler` and `#property` lines in
+/// the config.
+#[derive(Default)]
+pub struct Classes(pub HashMap<String, Class>);
+
+impl Classes {
+    pub fn get(&self, name: &str) -> Option<&Class> {
+        self.0.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// The class and its ancestors, nearest first.
+    pub fn ancestry(&self, name: &str) -> Vec<(&str, &Class)> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        let mut next = self.0.get_key_value(name);
+
+        while let Some((name, class)) = next {
+            let name = name.as_str();
+            // a class inheriting from itself is reported by the config
+            if !seen.insert(name) {
+                break;
+            }
+            chain.push((name, class));
+            next = class
+                .parent
+                .as_deref()
+                .and_then(|parent| self.0.get_key_value(parent));
+        }
+
+        chain
+    }
+
+    /// The handler table of the nearest class in `name`'s ancestry that
+    /// defines `handler`.
+    pub fn find_handler(&self, name: &str, handler: &str) -> Option<&Function> {
+        self.ancestry(name)
+            .into_iter()
+            .map(|(_, class)| &class.handlers)
+            .find(|handlers| handlers.children.contains_key(handler))
+    }
+
+    /// The property table of the nearest class in `name`'s ancestry that
+    /// defines `key`.
+    pub fn find_property(&self, name: &str, key: &str) -> Option<&Function> {
+        self.ancestry(name)
+            .into_iter()
+            .map(|(_, class)| &class.properties)
+            .find(|properties| properties.children.contains_key(key))
+    }
+}
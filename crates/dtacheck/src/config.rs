@@ -0,0 +1,733 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+use arson_parse::reporting as codespan_reporting;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Label;
+
+use crate::classes::Class;
+use crate::classes::Classes;
+use crate::linter::ArgKind;
+use crate::linter::Function;
+use crate::linter::Level;
+use crate::linter::LINT_IDS;
+
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+#[derive(Default)]
+pub struct Config {
+    // signatures, and those added with `#overload <path> : <params>`
+    pub funcs: Function,
+    // severity overrides set with `#lint <id> <allow|warn|deny>`
+    pub levels: HashMap<String, Level>,
+    // macros defined outside the linted files, set with `#macro <names>`
+    pub macros: HashSet<String>,
+    // variables set by the engine, set with `#global <$names>`
+    pub globals: HashSet<String>,
+    // set with `#class <name> [parent]`, and `#handler <class> <signature>`
+    // or `#property <class> <signature>`
+    pub classes: Classes,
+    // classes of objects made outside the linted files, such as in `.milo`
+    // scenes, so that their `(name ...)` definitions can be checked. set
+    // with `#object <name> <class>`
+    pub objects: HashMap<String, String>,
+    pub files: Vec<ConfigFile>,
+    // file ids in these index into `files`
+    pub diagnostics: Vec<Diagnostic<usize>>,
+}
+
+type Signature = (usize, usize, Vec<ArgKind>);
+// a message and the range in the line it applies to
+type ParseError = (String, Range<usize>);
+
+struct Definition {
+    file: usize,
+    range: Range<usize>,
+    signature: Signature,
+}
+
+struct Token<'a> {
+    text: &'a str,
+    range: Range<usize>,
+}
+
+fn tokenize(line: &str, offset: usize) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                tokens.push(Token {
+                    text: &line[s..i],
+                    range: offset + s..offset + i,
+                });
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    tokens
+}
+
+// parses typed parameters such as `int symbol? any...` into the minimum
+// and maximum argument counts and the kind of each parameter. when
+// `literals` is given, required parameters can be written `=value`, and
+// are collected there with their positions
+fn parse_params(
+    tokens: &[Token],
+    mut literals: Option<&mut Vec<(usize, String)>>,
+) -> Result<Signature, ParseError> {
+    let mut min_args = 0;
+    let mut max_args = 0;
+    let mut params = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let error = |message: String| (message, token.range.clone());
+
+        if let Some(kind) = token.text.strip_suffix("...") {
+            if i != tokens.len() - 1 {
+                return Err(error(format!(
+                    "variadic parameter `{}` must come last",
+                    token.text
+                )));
+            }
+            params.push(kind.parse().map_err(error)?);
+            max_args = usize::MAX;
+        } else if let Some(kind) = token.text.strip_suffix('?') {
+            params.push(kind.parse().map_err(error)?);
+            max_args += 1;
+        } else {
+            if max_args != min_args {
+                return Err(error(format!(
+                    "required parameter `{}` follows an optional one",
+                    token.text
+                )));
+            }
+            let kind = match (token.text.strip_prefix('='), &mut literals) {
+                (Some(""), Some(_)) => {
+                    return Err(error("expected a value after `=`".into()))
+                }
+                (Some(literal), Some(literals)) => {
+                    literals.push((i, literal.to_string()));
+                    literal_kind(literal)
+                }
+                _ => token.text.parse().map_err(error)?,
+            };
+            params.push(kind);
+            min_args += 1;
+            max_args += 1;
+        }
+    }
+
+    Ok((min_args, max_args, params))
+}
+
+fn parse_count(token: &Token) -> Result<usize, ParseError> {
+    token.text.parse::<usize>().map_err(|_| {
+        (
+            format!("expected an argument count, found `{}`", token.text),
+            token.range.clone(),
+        )
+    })
+}
+
+// lines are only parsed this way once they have at least three tokens
+fn parse_legacy(tokens: &[Token]) -> Result<Signature, ParseError> {
+    let len = tokens.len();
+    let min_args = parse_count(&tokens[len - 2])?;
+    let max_args = parse_count(&tokens[len - 1])?;
+    if min_args > max_args {
+        let range = tokens[len - 2].range.start..tokens[len - 1].range.end;
+        return Err((
+            "minimum argument count is larger than the maximum".to_string(),
+            range,
+        ));
+    }
+
+    Ok((min_args, max_args, Vec::new()))
+}
+
+// the kind of argument a literal in an overload is written as
+fn literal_kind(literal: &str) -> ArgKind {
+    if literal.parse::<i64>().is_ok() {
+        ArgKind::Int
+    } else if literal.parse::<f64>().is_ok() {
+        ArgKind::Float
+    } else if literal.starts_with('"') {
+        ArgKind::String
+    } else {
+        ArgKind::Symbol
+    }
+}
+
+// splits a line into the function path and its signature
+fn parse_signature<'t, 'a>(
+    tokens: &'t [Token<'a>],
+) -> Result<(&'t [Token<'a>], Signature), ParseError> {
+    // typed signatures separate the path from the parameters with `:`
+    match tokens.iter().position(|t| t.text == ":") {
+        Some(0) => Err((
+            "expected a function name before `:`".to_string(),
+            tokens[0].range.clone(),
+        )),
+        Some(sep) => parse_params(&tokens[sep + 1..], None)
+            .map(|signature| (&tokens[..sep], signature)),
+        None => parse_legacy(tokens)
+            .map(|signature| (&tokens[..tokens.len() - 2], signature)),
+    }
+}
+
+struct Loader {
+    config: Config,
+    loaded: HashSet<PathBuf>,
+    definitions: HashMap<Vec<String>, Definition>,
+    // where each class names its parent, checked once everything is loaded
+    parents: Vec<(String, usize, Range<usize>)>,
+    // `#overload`s by path, attached once everything is loaded so that a
+    // plain signature for the same path can be added as one of them
+    overloads: HashMap<Vec<String>, Vec<Function>>,
+}
+
+impl Loader {
+    fn new() -> Self {
+        Self {
+            config: Config::default(),
+            loaded: HashSet::new(),
+            definitions: HashMap::new(),
+            parents: Vec::new(),
+            overloads: HashMap::new(),
+        }
+    }
+
+    fn error(&mut self, file: usize, range: Range<usize>, message: String) {
+        self.config.diagnostics.push(
+            Diagnostic::error()
+                .with_message(message)
+                .with_labels(vec![Label::primary(file, range)]),
+        );
+    }
+
+    fn warning(&mut self, file: usize, range: Range<usize>, message: String) {
+        self.config.diagnostics.push(
+            Diagnostic::warning()
+                .with_message(message)
+                .with_labels(vec![Label::primary(file, range)]),
+        );
+    }
+
+    fn load(&mut self, path: &Path) -> io::Result<usize> {
+        let text = fs::read_to_string(path)?;
+        Ok(self.load_text(path, text))
+    }
+
+    fn load_text(&mut self, path: &Path, text: String) -> usize {
+        let file = self.config.files.len();
+        self.config.files.push(ConfigFile {
+            path: path.to_path_buf(),
+            text,
+        });
+        self.loaded
+            .insert(path.canonicalize().unwrap_or(path.to_path_buf()));
+
+        let text = self.config.files[file].text.clone();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let tokens = tokenize(line, offset);
+            offset += line.len();
+            self.load_line(file, path, &tokens);
+        }
+
+        file
+    }
+
+    fn load_line(&mut self, file: usize, path: &Path, tokens: &[Token]) {
+        let Some(first) = tokens.first() else {
+            return;
+        };
+
+        match first.text {
+            "#include" => return self.include(file, path, tokens),
+            "#lint" => return self.lint_level(file, tokens),
+            "#macro" => return self.macros(file, tokens),
+            "#global" => return self.globals(file, tokens),
+            "#class" => return self.class(file, tokens),
+            "#handler" => return self.member(file, tokens, false),
+            "#property" => return self.member(file, tokens, true),
+            "#object" => return self.object(file, tokens),
+            "#overload" => return self.overload(file, tokens),
+            _ => (),
+        }
+
+        if first.text.starts_with('#') {
+            return;
+        }
+
+        // older configs could hold lines too short to be a signature, which
+        // were skipped, so they still are
+        if tokens.len() < 3 && tokens.iter().all(|t| t.text != ":") {
+            let range = first.range.start..tokens[tokens.len() - 1].range.end;
+            self.warning(
+                file,
+                range,
+                "ignoring a line without `min max` or `: params` after the \
+                 function name"
+                    .into(),
+            );
+            return;
+        }
+
+        match parse_signature(tokens) {
+            Ok((path, signature)) => self.define(file, path, signature),
+            Err((message, range)) => self.error(file, range, message),
+        }
+    }
+
+    fn include(&mut self, file: usize, path: &Path, tokens: &[Token]) {
+        let directive = &tokens[0];
+        let [_, target] = tokens else {
+            let range =
+                directive.range.start..tokens[tokens.len() - 1].range.end;
+            self.error(
+                file,
+                range,
+                "expected one path after `#include`".into(),
+            );
+            return;
+        };
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let target_path = dir.join(target.text);
+        let canonical =
+            target_path.canonicalize().unwrap_or(target_path.clone());
+        if self.loaded.contains(&canonical) {
+            return;
+        }
+
+        if let Err(e) = self.load(&target_path) {
+            self.error(
+                file,
+                target.range.clone(),
+                format!("could not read `{}`: {e}", target_path.display()),
+            );
+        }
+    }
+
+    fn lint_level(&mut self, file: usize, tokens: &[Token]) {
+        let [_, id, level] = tokens else {
+            let range =
+                tokens[0].range.start..tokens[tokens.len() - 1].range.end;
+            self.error(
+                file,
+                range,
+                "expected `#lint <id> <allow|warn|deny>`".into(),
+            );
+            return;
+        };
+
+        if !LINT_IDS.contains(&id.text) {
+            self.error(
+                file,
+                id.range.clone(),
+                format!("unknown lint `{}`", id.text),
+            );
+            return;
+        }
+
+        match level.text.parse() {
+            Ok(level) => {
+                self.config.levels.insert(id.text.to_string(), level);
+            }
+            Err(message) => self.error(file, level.range.clone(), message),
+        }
+    }
+
+    fn macros(&mut self, file: usize, tokens: &[Token]) {
+        if tokens.len() < 2 {
+            self.error(
+                file,
+                tokens[0].range.clone(),
+                "expected macro names after `#macro`".into(),
+            );
+            return;
+        }
+
+        for token in &tokens[1..] {
+            self.config.macros.insert(token.text.to_string());
+        }
+    }
+
+    fn globals(&mut self, file: usize, tokens: &[Token]) {
+        if tokens.len() < 2 {
+            self.error(
+                file,
+                tokens[0].range.clone(),
+                "expected variable names after `#global`".into(),
+            );
+            return;
+        }
+
+        for token in &tokens[1..] {
+            let name = token.text.trim_start_matches('$');
+            self.config.globals.insert(name.to_string());
+        }
+    }
+
+    fn class(&mut self, file: usize, tokens: &[Token]) {
+        let (name, parent) = match tokens {
+            [_, name] => (name, None),
+            [_, name, parent] => (name, Some(parent)),
+            _ => {
+                let range =
+                    tokens[0].range.start..tokens[tokens.len() - 1].range.end;
+                self.error(
+                    file,
+                    range,
+                    "expected `#class <name> [parent]`".into(),
+                );
+                return;
+            }
+        };
+
+        if self.config.classes.contains(name.text) {
+            self.error(
+                file,
+                name.range.clone(),
+                format!("class `{}` is already defined", name.text),
+            );
+            return;
+        }
+
+        if let Some(parent) = parent {
+            self.parents.push((
+                name.text.to_string(),
+                file,
+                parent.range.clone(),
+            ));
+        }
+        self.config.classes.0.insert(
+            name.text.to_string(),
+            Class {
+                parent: parent.map(|p| p.text.to_string()),
+                handlers: Function::default(),
+                properties: Function::default(),
+            },
+        );
+    }
+
+    // `#handler` and `#property` lines, which share the function syntax
+    fn member(&mut self, file: usize, tokens: &[Token], property: bool) {
+        let [directive, class, signature @ ..] = tokens else {
+            self.error(
+                file,
+                tokens[0].range.clone(),
+                format!("expected a class after `{}`", tokens[0].text),
+            );
+            return;
+        };
+        if signature.is_empty() {
+            self.error(
+                file,
+                directive.range.start..class.range.end,
+                format!("expected a signature after `{}`", class.text),
+            );
+            return;
+        }
+        if !self.config.classes.contains(class.text) {
+            self.error(
+                file,
+                class.range.clone(),
+                format!("unknown class `{}`", class.text),
+            );
+            return;
+        }
+
+        let (path, (min_args, max_args, params)) =
+            match parse_signature(signature) {
+                Ok(signature) => signature,
+                Err((message, range)) => {
+                    return self.error(file, range, message)
+                }
+            };
+        let path = path.iter().map(|t| t.text).collect::<Vec<_>>();
+        if let Some(class) = self.config.classes.0.get_mut(class.text) {
+            let members = if property {
+                &mut class.properties
+            } else {
+                &mut class.handlers
+            };
+            members.insert(&path, min_args, max_args, params);
+        }
+    }
+
+    fn object(&mut self, file: usize, tokens: &[Token]) {
+        let [_, name, class] = tokens else {
+            let range =
+                tokens[0].range.start..tokens[tokens.len() - 1].range.end;
+            self.error(file, range, "expected `#object <name> <class>`".into());
+            return;
+        };
+        if !self.config.classes.contains(class.text) {
+            self.error(
+                file,
+                class.range.clone(),
+                format!("unknown class `{}`", class.text),
+            );
+            return;
+        }
+
+        self.config
+            .objects
+            .insert(name.text.to_string(), class.text.to_string());
+    }
+
+    // parents can be declared after their children, so are only checked at
+    // the end
+    fn check_parents(&mut self) {
+        for (name, file, range) in std::mem::take(&mut self.parents) {
+            let classes = &self.config.classes;
+            let parent = classes.get(&name).and_then(|c| c.parent.clone());
+            let Some(parent) = parent else {
+                continue;
+            };
+
+            if !classes.contains(&parent) {
+                self.error(file, range, format!("unknown class `{parent}`"));
+            } else if classes
+                .ancestry(&parent)
+                .iter()
+                .any(|(ancestor, _)| *ancestor == name)
+            {
+                self.error(
+                    file,
+                    range,
+                    format!("class `{name}` inherits from itself"),
+                );
+            }
+        }
+    }
+
+    // `#overload <path> : <params>`, where params written `=value` pick
+    // the overload when the argument there is written as that value
+    fn overload(&mut self, file: usize, tokens: &[Token]) {
+        let sep = tokens.iter().position(|t| t.text == ":");
+        let (path, params) = match sep {
+            Some(sep) if sep > 1 => (&tokens[1..sep], &tokens[sep + 1..]),
+            _ => {
+                let range =
+                    tokens[0].range.start..tokens[tokens.len() - 1].range.end;
+                self.error(
+                    file,
+                    range,
+                    "expected `#overload <name> : <params>`".into(),
+                );
+                return;
+            }
+        };
+
+        let mut literals = Vec::new();
+        let (min_args, max_args, params) =
+            match parse_params(params, Some(&mut literals)) {
+                Ok(signature) => signature,
+                Err((message, range)) => {
+                    return self.error(file, range, message)
+                }
+            };
+
+        let key = path.iter().map(|t| t.text.to_string()).collect::<Vec<_>>();
+        let overload = Function {
+            min_args,
+            max_args,
+            params,
+            literals,
+            ..Function::default()
+        };
+        self.overloads.entry(key).or_default().push(overload);
+    }
+
+    // a plain signature for an overloaded path is checked first, as the
+    // overload that takes no literals
+    fn attach_overloads(&mut self) {
+        for (key, mut overloads) in std::mem::take(&mut self.overloads) {
+            if let Some(existing) = self.definitions.get(&key) {
+                let (min_args, max_args, params) = existing.signature.clone();
+                let base = Function {
+                    min_args,
+                    max_args,
+                    params,
+                    ..Function::default()
+                };
+                overloads.insert(0, base);
+            }
+            let path = key.iter().map(String::as_str).collect::<Vec<_>>();
+            self.config.funcs.set_overloads(&path, overloads);
+        }
+    }
+
+    fn define(&mut self, file: usize, path: &[Token], signature: Signature) {
+        let range = path[0].range.start..path[path.len() - 1].range.end;
+        let key = path.iter().map(|t| t.text.to_string()).collect::<Vec<_>>();
+
+        if let Some(existing) = self.definitions.get(&key) {
+            if existing.signature != signature {
+                let diag = Diagnostic::error()
+                    .with_message(format!(
+                        "conflicting signatures for `{}`",
+                        key.join(" ")
+                    ))
+                    .with_labels(vec![
+                        Label::primary(file, range),
+                        Label::secondary(existing.file, existing.range.clone())
+                            .with_message("previously defined here"),
+                    ]);
+                self.config.diagnostics.push(diag);
+            }
+            return;
+        }
+
+        let path = key.iter().map(String::as_str).collect::<Vec<_>>();
+        let (min_args, max_args, params) = signature.clone();
+        self.config.funcs.insert(&path, min_args, max_args, params);
+        self.definitions.insert(
+            key,
+            Definition {
+                file,
+                range,
+                signature,
+            },
+        );
+    }
+}
+
+impl Config {
+    /// Loads a function config and everything it includes. Problems inside
+    /// the config are reported as diagnostics rather than errors, only
+    /// failing to read the top level file is an error.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut loader = Loader::new();
+        loader.load(path)?;
+        loader.check_parents();
+        loader.attach_overloads();
+        Ok(loader.config)
+    }
+
+    /// Parses a function config held in memory. `path` names it in
+    /// diagnostics, and its `#include`s are looked for relative to it.
+    pub fn parse(path: &Path, text: &str) -> Self {
+        let mut loader = Loader::new();
+        loader.load_text(path, text.to_string());
+        loader.check_parents();
+        loader.attach_overloads();
+        loader.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Severity;
+
+    use super::*;
+
+    // each diagnostic's message and the line of its primary label
+    fn problems(config: &Config) -> Vec<(String, usize)> {
+        config
+            .diagnostics
+            .iter()
+            .map(|diag| {
+                let label = &diag.labels[0];
+                let text = &config.files[label.file_id].text;
+                let line = text[..label.range.start].matches('\n').count() + 1;
+                (diag.message.clone(), line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_errors_name_their_line() {
+        let text = "foo 0 1\nbar : wat\n\nbaz 2 1\n";
+        let config = Config::parse(Path::new("fns"), text);
+        assert_eq!(
+            problems(&config),
+            [
+                ("unknown argument kind `wat`".to_string(), 2),
+                (
+                    "minimum argument count is larger than the maximum"
+                        .to_string(),
+                    4
+                ),
+            ]
+        );
+        assert_eq!(config.diagnostics[1].labels[0].range, 23..26);
+    }
+
+    #[test]
+    fn short_legacy_lines_are_skipped_with_a_warning() {
+        let config = Config::parse(Path::new("fns"), "foo\nbar 1\nbaz 0 1\n");
+        let severities = config
+            .diagnostics
+            .iter()
+            .map(|diag| diag.severity)
+            .collect::<Vec<_>>();
+        assert_eq!(severities, [Severity::Warning, Severity::Warning]);
+        assert!(!config.funcs.children.contains_key("foo"));
+        assert!(config.funcs.children.contains_key("baz"));
+    }
+
+    #[test]
+    fn conflicting_signatures_are_reported() {
+        let text = "foo : int\nfoo : int\nfoo : symbol\n";
+        let config = Config::parse(Path::new("fns"), text);
+        assert_eq!(
+            problems(&config),
+            [("conflicting signatures for `foo`".to_string(), 3)]
+        );
+        let previous = &config.diagnostics[0].labels[1];
+        assert_eq!(previous.range, 0..3);
+        assert_eq!(config.funcs.children["foo"].signature(), "int");
+    }
+
+    #[test]
+    fn includes_are_relative_to_the_including_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("fns"), "#include sub/more\nfoo 0 1\n")
+            .unwrap();
+        fs::write(dir.path().join("sub/more"), "#include last\nbar 1 1\n")
+            .unwrap();
+        fs::write(dir.path().join("sub/last"), "baz 2 2\n").unwrap();
+
+        let config = Config::load(&dir.path().join("fns")).unwrap();
+        assert!(config.diagnostics.is_empty());
+        assert_eq!(config.files.len(), 3);
+        for name in ["foo", "bar", "baz"] {
+            assert!(config.funcs.children.contains_key(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn include_problems_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let top = dir.path().join("fns");
+        fs::write(&top, "#include other\n#include missing\nfoo 0 1\n").unwrap();
+        // a cycle back to the top file is only loaded once
+        fs::write(dir.path().join("other"), "#include fns\nbar 0 1\n").unwrap();
+
+        let config = Config::load(&top).unwrap();
+        assert_eq!(config.files.len(), 2);
+        let problems = problems(&config);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].0.starts_with("could not read `"));
+        assert_eq!(problems[0].1, 2);
+        assert_eq!(config.diagnostics[0].labels[0].file_id, 0);
+    }
+}
@@ -0,0 +1,299 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+use std::str;
+use std::str::FromStr;
+
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Label;
+use encoding_rs::UTF_8;
+use encoding_rs::WINDOWS_1252;
+
+use crate::linter::Lint;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    // decoded as Windows-1252, which Latin-1 text reads the same as
+    Latin1,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Utf8 => "UTF-8",
+            Self::Latin1 => "Latin-1",
+        })
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            "latin-1" | "latin1" | "windows-1252" | "cp1252" => {
+                Ok(Self::Latin1)
+            }
+            _ => Err(format!(
+                "unknown encoding `{s}`, expected `utf-8` or `latin-1`"
+            )),
+        }
+    }
+}
+
+/// A file's text and how it was decoded.
+pub struct Decoded {
+    pub text: String,
+    pub encoding: Encoding,
+    // in `text`, the first bytes that were in a different encoding from
+    // the rest of the file
+    pub mixed: Option<Range<usize>>,
+}
+
+impl From<String> for Decoded {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            encoding: Encoding::Utf8,
+            mixed: None,
+        }
+    }
+}
+
+// the first complete multi-byte UTF-8 sequence, which in a Latin-1 file
+// is almost certainly text pasted in from somewhere else
+fn find_utf8(bytes: &[u8]) -> Option<Range<usize>> {
+    (0..bytes.len()).find_map(|i| {
+        let len = match bytes[i] {
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => return None,
+        };
+        let sequence = bytes.get(i..i + len)?;
+        str::from_utf8(sequence).ok().map(|_| i..i + len)
+    })
+}
+
+fn decoded_len(bytes: &[u8]) -> usize {
+    WINDOWS_1252.decode_without_bom_handling(bytes).0.len()
+}
+
+fn decode_latin1(bytes: &[u8]) -> Decoded {
+    let text = WINDOWS_1252.decode_without_bom_handling(bytes).0;
+    let mixed = find_utf8(bytes).map(|range| {
+        let start = decoded_len(&bytes[..range.start]);
+        start..start + decoded_len(&bytes[range])
+    });
+    Decoded {
+        text: text.into_owned(),
+        encoding: Encoding::Latin1,
+        mixed,
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> Decoded {
+    // invalid bytes become U+FFFD, which is three bytes long
+    let mixed = str::from_utf8(bytes)
+        .err()
+        .map(|e| e.valid_up_to()..e.valid_up_to() + 3);
+    let text = UTF_8.decode_without_bom_handling(bytes).0;
+    Decoded {
+        text: text.into_owned(),
+        encoding: Encoding::Utf8,
+        mixed,
+    }
+}
+
+/// Decodes a file in the given encoding, or guesses it when there is none.
+/// Files with a byte order mark or that are valid UTF-8 are taken to be
+/// UTF-8, and anything else to be Latin-1.
+pub fn decode(bytes: &[u8], encoding: Option<Encoding>) -> Decoded {
+    if let Some(bytes) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        return decode_utf8(bytes);
+    }
+
+    match encoding {
+        Some(Encoding::Utf8) => decode_utf8(bytes),
+        Some(Encoding::Latin1) => decode_latin1(bytes),
+        None if str::from_utf8(bytes).is_ok() => decode_utf8(bytes),
+        None => decode_latin1(bytes),
+    }
+}
+
+/// Encodes text to be written back to a file decoded as `encoding`. Text
+/// decoded as Latin-1 comes back byte for byte.
+pub fn encode(text: &str, encoding: Encoding) -> Cow<'_, [u8]> {
+    match encoding {
+        Encoding::Utf8 => Cow::Borrowed(text.as_bytes()),
+        Encoding::Latin1 => WINDOWS_1252.encode(text).0,
+    }
+}
+
+pub enum EncodingLint {
+    Mixed(Encoding, Range<usize>),
+    Unrenderable(char, Range<usize>),
+}
+
+impl Lint for EncodingLint {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        match self {
+            Self::Mixed(Encoding::Latin1, range) => Diagnostic::warning()
+                .with_message("file is Latin-1 but contains UTF-8 text")
+                .with_labels(vec![Label::primary(id, range.clone())
+                    .with_message("this was UTF-8")]),
+            Self::Mixed(Encoding::Utf8, range) => Diagnostic::warning()
+                .with_message("file is UTF-8 but contains invalid bytes")
+                .with_labels(vec![Label::primary(id, range.clone())
+                    .with_message("this is not UTF-8")]),
+            Self::Unrenderable(c, range) => Diagnostic::warning()
+                .with_message(format!(
+                    "`{c}` (U+{:04X}) cannot be shown in game",
+                    u32::from(*c)
+                ))
+                .with_labels(vec![Label::primary(id, range.clone())
+                    .with_message("not a Windows-1252 character")]),
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Mixed(..) => "mixed-encoding",
+            Self::Unrenderable(..) => "unrenderable-character",
+        }
+    }
+}
+
+// the games' fonts only cover Windows-1252
+fn is_renderable(c: char) -> bool {
+    let mut buf = [0; 4];
+    let (_, _, unmappable) = WINDOWS_1252.encode(c.encode_utf8(&mut buf));
+    !unmappable
+}
+
+/// Finds characters in strings and symbols that the game has no glyph
+/// for, one per node.
+pub fn lint_unrenderable(
+    lints: &mut Vec<Box<dyn Lint>>,
+    text: &str,
+    ast: &[Expression],
+) {
+    for node in ast {
+        match &node.value {
+            ExpressionValue::String(_) | ExpressionValue::Symbol(_) => {
+                let start = node.location.start;
+                let source = text.get(node.location.clone()).unwrap_or("");
+                // bytes that couldn't be decoded are reported as mixed
+                // encodings instead
+                let found = source.char_indices().find(|(_, c)| {
+                    *c != char::REPLACEMENT_CHARACTER && !is_renderable(*c)
+                });
+                if let Some((i, c)) = found {
+                    let range = start + i..start + i + c.len_utf8();
+                    lints.push(Box::new(EncodingLint::Unrenderable(c, range)));
+                }
+            }
+            ExpressionValue::Array(array)
+            | ExpressionValue::Command(array)
+            | ExpressionValue::Property(array) => {
+                lint_unrenderable(lints, text, array)
+            }
+            ExpressionValue::Define(_, array)
+            | ExpressionValue::Autorun(array) => {
+                lint_unrenderable(lints, text, &array.exprs)
+            }
+            ExpressionValue::Conditional {
+                true_branch,
+                false_branch,
+                ..
+            } => {
+                lint_unrenderable(lints, text, &true_branch.exprs);
+                if let Some(false_branch) = false_branch {
+                    lint_unrenderable(lints, text, &false_branch.exprs);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arson_parse::ParseOptions;
+
+    use super::*;
+
+    #[test]
+    fn valid_utf8_is_guessed_as_utf8() {
+        let decoded = decode("café".as_bytes(), None);
+        assert_eq!(decoded.encoding, Encoding::Utf8);
+        assert_eq!(decoded.text, "café");
+        assert_eq!(decoded.mixed, None);
+
+        let decoded = decode(b"\xef\xbb\xbf(a)", Some(Encoding::Latin1));
+        assert_eq!(decoded.encoding, Encoding::Utf8);
+        assert_eq!(decoded.text, "(a)");
+    }
+
+    #[test]
+    fn anything_else_is_guessed_as_windows_1252() {
+        let decoded = decode(b"caf\xe9 \x80", None);
+        assert_eq!(decoded.encoding, Encoding::Latin1);
+        assert_eq!(decoded.text, "café €");
+        assert_eq!(decoded.mixed, None);
+
+        // a given encoding is used even when the text is valid UTF-8
+        let decoded = decode("é".as_bytes(), Some(Encoding::Latin1));
+        assert_eq!(decoded.text, "Ã©");
+    }
+
+    #[test]
+    fn mixed_ranges_are_in_the_decoded_text() {
+        // UTF-8 pasted into a Latin-1 file
+        let decoded = decode(b"caf\xe9 \xc3\xa9!", None);
+        let mixed = decoded.mixed.unwrap();
+        assert_eq!(mixed, 6..10);
+        assert_eq!(&decoded.text[mixed], "Ã©");
+
+        // invalid bytes in a UTF-8 file become a replacement character
+        let decoded = decode(b"ab\xffc", Some(Encoding::Utf8));
+        let mixed = decoded.mixed.unwrap();
+        assert_eq!(&decoded.text[mixed], "\u{fffd}");
+    }
+
+    #[test]
+    fn characters_without_a_glyph_are_reported_once_per_node() {
+        let text = "(\"snow ☃☃\" café \"\u{fffd}\" (sym☂))";
+        let options = ParseOptions {
+            include_comments: false,
+        };
+        let ast = arson_parse::parse_text(text, options).unwrap();
+        let mut lints = Vec::new();
+        lint_unrenderable(&mut lints, text, &ast);
+
+        let ranges = lints
+            .iter()
+            .map(|lint| {
+                let diag = lint.to_codespan(0);
+                assert_eq!(lint.id(), "unrenderable-character");
+                &text[diag.labels[0].range.clone()]
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, ["☃", "☂"]);
+    }
+
+    #[test]
+    fn latin1_files_encode_back_byte_for_byte() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+        let decoded = decode(&bytes, Some(Encoding::Latin1));
+        assert_eq!(encode(&decoded.text, Encoding::Latin1), bytes);
+
+        let text = "café ☃";
+        assert_eq!(encode(text, Encoding::Utf8), text.as_bytes());
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+
+use crate::linter::ArgKind;
+use crate::macros::is_macro_name;
+
+// what an argument was seen to be. values only known once the script runs
+// say nothing about what a parameter expects
+fn observed_kind(expr: &Expression) -> Option<ArgKind> {
+    match expr.value {
+        ExpressionValue::Integer(_) => Some(ArgKind::Int),
+        ExpressionValue::Float(_) => Some(ArgKind::Float),
+        ExpressionValue::String(_) => Some(ArgKind::String),
+        ExpressionValue::Symbol(_) => Some(ArgKind::Symbol),
+        ExpressionValue::Array(_) => Some(ArgKind::Array),
+        _ => None,
+    }
+}
+
+// the narrowest kind covering everything seen in one position
+fn merge(a: Option<ArgKind>, b: Option<ArgKind>) -> Option<ArgKind> {
+    match (a, b) {
+        (None, kind) | (kind, None) => kind,
+        (Some(a), Some(b)) if a == b => Some(a),
+        (
+            Some(ArgKind::Int | ArgKind::Float | ArgKind::Number),
+            Some(ArgKind::Int | ArgKind::Float | ArgKind::Number),
+        ) => Some(ArgKind::Number),
+        _ => Some(ArgKind::Any),
+    }
+}
+
+fn symbol_name<'a>(node: &'a Expression) -> Option<&'a str> {
+    match &node.value {
+        ExpressionValue::Symbol(sym) => Some(sym.as_ref()),
+        _ => None,
+    }
+}
+
+// a head always followed by a symbol may just take an enum-like first
+// argument, so it is only guessed to be an object when it was seen with
+// several different messages over enough calls
+const MIN_OBJECT_CALLS: usize = 3;
+
+#[derive(Default)]
+struct Calls {
+    min_args: usize,
+    max_args: usize,
+    kinds: Vec<Option<ArgKind>>,
+    // the message each call sends, when the head looks like an object
+    messages: BTreeMap<String, Calls>,
+    // whether every call passed a symbol that isn't a macro first
+    sends_messages: bool,
+    count: usize,
+}
+
+impl Calls {
+    fn record(&mut self, args: &[Expression]) {
+        if self.count == 0 {
+            self.min_args = args.len();
+            self.sends_messages = true;
+        }
+        self.count += 1;
+        self.min_args = self.min_args.min(args.len());
+        self.max_args = self.max_args.max(args.len());
+        if self.kinds.len() < args.len() {
+            self.kinds.resize(args.len(), None);
+        }
+        for (kind, arg) in self.kinds.iter_mut().zip(args) {
+            *kind = merge(*kind, observed_kind(arg));
+        }
+    }
+
+    fn record_message(&mut self, args: &[Expression]) {
+        match args.first().and_then(symbol_name) {
+            Some(message) if !is_macro_name(message) => self
+                .messages
+                .entry(message.to_string())
+                .or_default()
+                .record(&args[1..]),
+            _ => self.sends_messages = false,
+        }
+    }
+
+    fn is_object(&self) -> bool {
+        self.sends_messages
+            && self.messages.len() > 1
+            && self.count >= MIN_OBJECT_CALLS
+    }
+
+    // typed parameters as written in `.dtacheckfns`
+    fn signature(&self) -> String {
+        self.kinds
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                let kind = kind.unwrap_or(ArgKind::Any);
+                if i < self.min_args {
+                    kind.to_string()
+                } else {
+                    format!("{kind}?")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Every command called across a corpus of scripts, with the argument
+/// counts and kinds each was called with.
+#[derive(Default)]
+pub struct Inference {
+    calls: HashMap<String, Calls>,
+    files: usize,
+}
+
+impl Inference {
+    /// Records every command in a file, including those in every branch
+    /// of its conditionals.
+    pub fn add_file(&mut self, ast: &[Expression]) {
+        self.files += 1;
+        self.collect(ast);
+    }
+
+    fn collect(&mut self, ast: &[Expression]) {
+        for node in ast {
+            match &node.value {
+                ExpressionValue::Command(array) => {
+                    if let Some(head) = array.first().and_then(symbol_name) {
+                        let calls =
+                            self.calls.entry(head.to_string()).or_default();
+                        calls.record(&array[1..]);
+                        calls.record_message(&array[1..]);
+                    }
+                    self.collect(array);
+                }
+                ExpressionValue::Array(array)
+                | ExpressionValue::Property(array) => self.collect(array),
+                ExpressionValue::Define(_, array)
+                | ExpressionValue::Autorun(array) => self.collect(&array.exprs),
+                ExpressionValue::Conditional {
+                    true_branch,
+                    false_branch,
+                    ..
+                } => {
+                    self.collect(&true_branch.exprs);
+                    if let Some(false_branch) = false_branch {
+                        self.collect(&false_branch.exprs);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// A draft config with a typed signature for every command seen,
+    /// sorted so it can be diffed against an existing one. Heads that were
+    /// always followed by one of several symbols are guessed to be objects,
+    /// and get an entry per message under a comment saying so.
+    pub fn config(&self) -> String {
+        let mut heads = self.calls.iter().collect::<Vec<_>>();
+        heads.sort_by_key(|(head, _)| *head);
+
+        let mut out = format!(
+            "# inferred from {} files by `dtacheck infer`\n",
+            self.files
+        );
+        let mut push = |line: String| {
+            out.push_str(line.trim_end());
+            out.push('\n');
+        };
+        for (head, calls) in heads {
+            if calls.is_object() {
+                push(format!(
+                    "# `{head}` was always sent a message, so is guessed to \
+                     be an object"
+                ));
+                for (message, calls) in &calls.messages {
+                    push(format!("{head} {message} : {}", calls.signature()));
+                }
+            } else {
+                push(format!("{head} : {}", calls.signature()));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use arson_parse::ParseOptions;
+
+    use super::*;
+    use crate::config::Config;
+
+    fn infer(files: &[&str]) -> String {
+        let mut inference = Inference::default();
+        for text in files {
+            let options = ParseOptions {
+                include_comments: false,
+            };
+            let ast = arson_parse::parse_text(text, options).unwrap();
+            inference.add_file(&ast);
+        }
+        inference.config()
+    }
+
+    #[test]
+    fn objects_need_several_messages_over_enough_calls() {
+        // only two calls
+        let config = infer(&["{mixer fade 1}\n{mixer mute}\n"]);
+        assert!(config.contains("\nmixer : symbol int?\n"), "{config}");
+
+        // always the same message, which may just be an enum-like argument
+        let config = infer(&["{mode fast}\n{mode fast}\n{mode fast}\n"]);
+        assert!(config.contains("\nmode : symbol\n"), "{config}");
+
+        // macros aren't messages
+        let config = infer(&["{play SONG}\n{play other}\n{play third 1}\n"]);
+        assert!(config.contains("\nplay : symbol int?\n"), "{config}");
+    }
+
+    #[test]
+    fn config_lists_every_head_sorted() {
+        let config = infer(&[
+            "{mixer fade 1 2.0}\n{mixer mute}\n{set $x \"a\"}\n",
+            "{mixer fade 2 3}\n{print}\n{print 1 (a)}\n",
+        ]);
+        assert_eq!(
+            config,
+            "# inferred from 2 files by `dtacheck infer`\n\
+             # `mixer` was always sent a message, so is guessed to be an \
+             object\n\
+             mixer fade : int number\n\
+             mixer mute :\n\
+             print : int? array?\n\
+             set : any string\n"
+        );
+    }
+
+    #[test]
+    fn printed_config_parses_back() {
+        let text = infer(&[
+            "{mixer fade 1 2.0}\n{mixer mute}\n{mixer fade 1 3}\n",
+            "{print}\n{print 1 \"a\"}\n{set $x 1}\n",
+        ]);
+        let config = Config::parse(Path::new("inferred"), &text);
+        assert!(config.diagnostics.is_empty(), "{text}");
+
+        let signature = |path: &[&str]| {
+            let mut func = &config.funcs;
+            for name in path {
+                func = &func.children[*name];
+            }
+            func.signature()
+        };
+        assert_eq!(signature(&["mixer", "fade"]), "int number");
+        assert_eq!(signature(&["mixer", "mute"]), "0 arguments");
+        assert_eq!(signature(&["print"]), "int? string?");
+        assert_eq!(signature(&["set"]), "any int");
+    }
+}
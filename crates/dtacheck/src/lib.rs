@@ -1 +1,15 @@
+pub mod assets;
+pub mod baseline;
+pub mod calls;
+pub mod check;
+pub mod classes;
+pub mod config;
+pub mod encoding;
+pub mod infer;
 pub mod linter;
+pub mod macros;
+pub mod output;
+pub mod preprocess;
+pub mod program;
+pub mod suppress;
+pub mod variables;
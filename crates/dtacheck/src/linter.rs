@@ -1,58 +1,327 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
 use std::ops::Range;
+use std::str::FromStr;
 
 use arson_parse::reporting as codespan_reporting;
 use arson_parse::Expression;
 use arson_parse::ExpressionValue;
 use codespan_reporting::diagnostic::Diagnostic;
 use codespan_reporting::diagnostic::Label;
+use codespan_reporting::diagnostic::LabelStyle;
+use codespan_reporting::diagnostic::Severity;
+
+use crate::assets::Assets;
+use crate::assets::MissingAsset;
+use crate::calls::handler_name;
+use crate::classes::Classes;
+use crate::suppress::Suppressions;
 
 pub trait Lint {
     fn to_codespan(&self, id: usize) -> Diagnostic<usize>;
+    // stable name used to configure and suppress the lint
+    fn id(&self) -> &'static str;
 }
 
-impl<'src> Lint for arson_parse::Diagnostic {
+impl Lint for arson_parse::Diagnostic {
     fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
         self.to_codespan(id)
     }
+
+    fn id(&self) -> &'static str {
+        "parse-error"
+    }
+}
+
+pub const LINT_IDS: &[&str] = &[
+    "parse-error",
+    "too-many-args",
+    "too-few-args",
+    "wrong-arg-kind",
+    "no-matching-overload",
+    "missing-fallthrough",
+    "unreachable-case",
+    "empty-case",
+    "unknown-lint",
+    "missing-include",
+    "include-cycle",
+    "undefined-macro",
+    "macro-redefined",
+    "unused-macro",
+    "undefined-variable",
+    "unused-variable",
+    "unknown-function",
+    "unknown-handler",
+    "unknown-property",
+    "duplicate-property",
+    "wrong-property-value",
+    "missing-asset",
+    "mixed-encoding",
+    "unrenderable-character",
+];
+
+// lints that only make sense when linting a whole project at once, or that
+// need a complete config to be useful, so have to be enabled with
+// `#lint <id> warn`
+pub const ALLOWED_BY_DEFAULT: &[&str] = &["unused-macro", "unknown-function"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
 }
 
-pub fn lint_file(ast: &[Expression], funcs: &Function) -> Vec<Box<dyn Lint>> {
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "deny" => Ok(Self::Deny),
+            _ => Err(format!(
+                "unknown lint level `{s}`, expected `allow`, `warn` or `deny`"
+            )),
+        }
+    }
+}
+
+/// Turns lints into diagnostics tagged with their lint ID, dropping those
+/// that are allowed or suppressed and applying configured severities.
+pub fn to_diagnostics(
+    lints: &[Box<dyn Lint>],
+    file_id: usize,
+    levels: &HashMap<String, Level>,
+    suppressions: &Suppressions,
+) -> Vec<Diagnostic<usize>> {
+    let mut diagnostics = Vec::new();
+
+    for lint in lints {
+        let id = lint.id();
+        let level = levels.get(id).copied().or_else(|| {
+            ALLOWED_BY_DEFAULT.contains(&id).then_some(Level::Allow)
+        });
+        if level == Some(Level::Allow) {
+            continue;
+        }
+
+        let mut diag = lint.to_codespan(file_id);
+        let pos = diag
+            .labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary)
+            .map(|label| label.range.start);
+        if pos.is_some_and(|pos| suppressions.is_suppressed(id, pos)) {
+            continue;
+        }
+
+        match level {
+            Some(Level::Warn) => diag.severity = Severity::Warning,
+            Some(Level::Deny) => diag.severity = Severity::Error,
+            _ => (),
+        }
+        diag.code = Some(id.to_string());
+        diagnostics.push(diag);
+    }
+
+    diagnostics
+}
+
+/// What two diagnostics share when they say the same thing about the same
+/// place, as happens when a file is linted more than once.
+#[derive(PartialEq, Eq, Hash)]
+pub struct DuplicateKey {
+    severity: Severity,
+    code: Option<String>,
+    message: String,
+    places: Vec<(bool, usize, Range<usize>)>,
+}
+
+impl DuplicateKey {
+    pub fn new(diag: &Diagnostic<usize>) -> Self {
+        let places = diag
+            .labels
+            .iter()
+            .map(|label| {
+                (
+                    label.style == LabelStyle::Primary,
+                    label.file_id,
+                    label.range.clone(),
+                )
+            })
+            .collect();
+        Self {
+            severity: diag.severity,
+            code: diag.code.clone(),
+            message: diag.message.clone(),
+            places,
+        }
+    }
+}
+
+pub fn lint_file<'a>(
+    ast: &'a [Expression],
+    funcs: &Function,
+    classes: &Classes,
+    objects: &'a HashMap<String, String>,
+    assets: Option<Assets>,
+) -> Vec<Box<dyn Lint>> {
+    let mut objects = objects
+        .iter()
+        .map(|(name, class)| (name.as_str(), class.as_str()))
+        .collect();
+    let mut handlers = HashSet::new();
+    collect_objects(&mut objects, &mut handlers, ast, classes);
+
+    let ctx = Context {
+        funcs,
+        classes,
+        this: None,
+        objects: &objects,
+        handlers: &handlers,
+        assets,
+    };
     let mut lints = Vec::new();
-    lint_node(&mut lints, ast, funcs);
+    lint_node(&mut lints, ast, ctx);
     lints
 }
 
-fn lint_node(
+// what is known about receivers while walking a file
+#[derive(Clone, Copy)]
+struct Context<'a> {
+    funcs: &'a Function,
+    classes: &'a Classes,
+    // class of `$this`, inside a class's block or a `new` command
+    this: Option<&'a str>,
+    // objects created with `{new Class name ...}` or declared in the
+    // config, by name
+    objects: &'a HashMap<&'a str, &'a str>,
+    // handlers defined in script, which any object may respond to
+    handlers: &'a HashSet<&'a str>,
+    // where to look for files named in `path` arguments, when a root is
+    // known
+    assets: Option<Assets<'a>>,
+}
+
+fn symbol_name<'a>(node: &'a Expression) -> Option<&'a str> {
+    match &node.value {
+        ExpressionValue::Symbol(sym) => Some(sym.as_ref()),
+        _ => None,
+    }
+}
+
+fn collect_objects<'a>(
+    objects: &mut HashMap<&'a str, &'a str>,
+    handlers: &mut HashSet<&'a str>,
+    ast: &'a [Expression],
+    classes: &Classes,
+) {
+    for node in ast {
+        match &node.value {
+            ExpressionValue::Command(array) => {
+                if let [head, class, name, ..] = array.as_slice() {
+                    if let (Some("new"), Some(class), Some(name)) = (
+                        symbol_name(head),
+                        symbol_name(class),
+                        symbol_name(name),
+                    ) {
+                        if classes.contains(class) {
+                            objects.insert(name, class);
+                        }
+                    }
+                }
+                collect_objects(objects, handlers, array, classes);
+            }
+            ExpressionValue::Array(array) => {
+                if let Some(name) = handler_name(array) {
+                    handlers.insert(name);
+                }
+                collect_objects(objects, handlers, array, classes);
+            }
+            ExpressionValue::Property(array) => {
+                collect_objects(objects, handlers, array, classes)
+            }
+            ExpressionValue::Define(_, array)
+            | ExpressionValue::Autorun(array) => {
+                collect_objects(objects, handlers, &array.exprs, classes)
+            }
+            _ => (),
+        }
+    }
+}
+
+fn lint_node<'a>(
     lints: &mut Vec<Box<dyn Lint>>,
-    ast: &[Expression],
-    funcs: &Function,
+    ast: &'a [Expression],
+    ctx: Context<'a>,
 ) {
     for node in ast {
         match &node.value {
-            ExpressionValue::Array(array)
-            | ExpressionValue::Property(array) => {
-                lint_node(lints, &array, funcs)
+            ExpressionValue::Array(array) => {
+                // `(object ...)` sets properties on an object made with `new`
+                // or declared with `#object`
+                let head = array.first().and_then(symbol_name);
+                if let Some(class) = head.and_then(|h| ctx.objects.get(h)) {
+                    lint_object(lints, class, &array[1..], ctx);
+                }
+
+                // `(ClassName ...)` blocks hold a class's handlers
+                let ctx = match head {
+                    Some(name) if ctx.classes.contains(name) => Context {
+                        this: Some(name),
+                        ..ctx
+                    },
+                    _ => ctx,
+                };
+                lint_node(lints, array, ctx)
             }
+            ExpressionValue::Property(array) => lint_node(lints, array, ctx),
             ExpressionValue::Define(_, array) => {
-                lint_node(lints, &array.exprs, funcs)
+                lint_node(lints, &array.exprs, ctx)
             }
             ExpressionValue::Command(array) => {
-                lint_node(lints, array, funcs);
+                // {new Class name (key values...) ...}
+                let new_class = match array.as_slice() {
+                    [head, class, ..] if symbol_name(head) == Some("new") => {
+                        symbol_name(class)
+                            .filter(|class| ctx.classes.contains(class))
+                    }
+                    _ => None,
+                };
+                let inner = match new_class {
+                    Some(class) => Context {
+                        this: Some(class),
+                        ..ctx
+                    },
+                    None => ctx,
+                };
+                lint_node(lints, array, inner);
+                if let (Some(class), Some(entries)) =
+                    (new_class, array.get(3..))
+                {
+                    lint_object(lints, class, entries, ctx);
+                }
 
                 let has_preprocessor_directive = array.iter().any(|e| {
                     matches!(e.value, ExpressionValue::Conditional { .. })
                 });
 
                 if !has_preprocessor_directive {
-                    lint_fn_args(lints, array, node.location.clone(), funcs);
-                    /*
+                    lint_fn_args(
+                        lints,
+                        array,
+                        node.location.clone(),
+                        ctx.funcs,
+                        ctx.assets,
+                    );
+                    lint_message(lints, array, node.location.clone(), ctx);
                     lint_switch_fallthrough(
                         lints,
                         array,
                         node.location.clone(),
                     );
-                    */
                 }
             }
             _ => (),
@@ -60,35 +329,499 @@ fn lint_node(
     }
 }
 
+// messages
+
+struct UnknownHandler {
+    class: String,
+    handler: String,
+    range: Range<usize>,
+}
+
+impl Lint for UnknownHandler {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        Diagnostic::warning()
+            .with_message(format!(
+                "`{}` has no handler `{}`",
+                self.class, self.handler
+            ))
+            .with_labels(vec![Label::primary(id, self.range.clone())])
+    }
+
+    fn id(&self) -> &'static str {
+        "unknown-handler"
+    }
+}
+
+// checks `{receiver handler args...}` when the receiver's class is known
+fn lint_message(
+    lints: &mut Vec<Box<dyn Lint>>,
+    stmt: &[Expression],
+    span: Range<usize>,
+    ctx: Context,
+) {
+    let [receiver, handler, ..] = stmt else {
+        return;
+    };
+
+    let class = match &receiver.value {
+        ExpressionValue::Variable(name) => {
+            let name: &str = name;
+            match name.trim_start_matches('$') {
+                "this" => ctx.this,
+                _ => None,
+            }
+        }
+        ExpressionValue::Symbol(sym) => ctx.objects.get(sym.as_ref()).copied(),
+        _ => None,
+    };
+    let (Some(class), Some(name)) = (class, symbol_name(handler)) else {
+        return;
+    };
+
+    match ctx.classes.find_handler(class, name) {
+        Some(handlers) => {
+            lint_fn_args(lints, &stmt[1..], span, handlers, ctx.assets)
+        }
+        None => {
+            // only classes with a schema can be checked, and handlers from
+            // script may be added to any object
+            let has_schema = ctx
+                .classes
+                .ancestry(class)
+                .iter()
+                .any(|(_, class)| !class.handlers.children.is_empty());
+            if has_schema && !ctx.handlers.contains(name) {
+                lints.push(Box::new(UnknownHandler {
+                    class: class.to_string(),
+                    handler: name.to_string(),
+                    range: handler.location.clone(),
+                }));
+            }
+        }
+    }
+}
+
+// properties
+
+enum PropertyLint {
+    Unknown {
+        class: String,
+        key: String,
+        range: Range<usize>,
+    },
+    Duplicate {
+        key: String,
+        range: Range<usize>,
+        previous: Range<usize>,
+    },
+    WrongCount {
+        key: String,
+        range: Range<usize>,
+        min: usize,
+        max: usize,
+    },
+    WrongKind {
+        key: String,
+        expected: ArgKind,
+        found: &'static str,
+        range: Range<usize>,
+    },
+}
+
+impl Lint for PropertyLint {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        match self {
+            Self::Unknown { class, key, range } => Diagnostic::warning()
+                .with_message(format!("`{class}` has no property `{key}`"))
+                .with_labels(vec![Label::primary(id, range.clone())]),
+            Self::Duplicate {
+                key,
+                range,
+                previous,
+            } => Diagnostic::warning()
+                .with_message(format!("property `{key}` is set twice"))
+                .with_labels(vec![
+                    Label::primary(id, range.clone()),
+                    Label::secondary(id, previous.clone())
+                        .with_message("first set here"),
+                ]),
+            Self::WrongCount {
+                key,
+                range,
+                min,
+                max,
+            } => {
+                let expected = if min == max {
+                    format!("{min}")
+                } else if *max == usize::MAX {
+                    format!("at least {min}")
+                } else {
+                    format!("{min} to {max}")
+                };
+                Diagnostic::error()
+                    .with_message(format!(
+                        "wrong number of values for property `{key}`"
+                    ))
+                    .with_labels(vec![Label::primary(id, range.clone())
+                        .with_message(format!("expected {expected}"))])
+            }
+            Self::WrongKind {
+                key,
+                expected,
+                found,
+                range,
+            } => Diagnostic::error()
+                .with_message(format!(
+                    "value of property `{key}` should be {}",
+                    expected.article()
+                ))
+                .with_labels(vec![Label::primary(id, range.clone())
+                    .with_message(format!(
+                        "expected {expected}, found {found}"
+                    ))]),
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Unknown { .. } => "unknown-property",
+            Self::Duplicate { .. } => "duplicate-property",
+            Self::WrongCount { .. } | Self::WrongKind { .. } => {
+                "wrong-property-value"
+            }
+        }
+    }
+}
+
+// checks the `(key values...)` entries of an object definition
+fn lint_object(
+    lints: &mut Vec<Box<dyn Lint>>,
+    class: &str,
+    entries: &[Expression],
+    ctx: Context,
+) {
+    let has_schema = ctx
+        .classes
+        .ancestry(class)
+        .iter()
+        .any(|(_, class)| !class.properties.children.is_empty());
+    let mut seen = HashMap::new();
+
+    for entry in entries {
+        let ExpressionValue::Array(array) = &entry.value else {
+            continue;
+        };
+        let Some(key) = array.first().and_then(symbol_name) else {
+            continue;
+        };
+        // handlers can be defined alongside properties
+        if handler_name(array).is_some() {
+            continue;
+        }
+
+        let range = array[0].location.clone();
+        if let Some(previous) = seen.insert(key, range.clone()) {
+            lints.push(Box::new(PropertyLint::Duplicate {
+                key: key.to_string(),
+                range: range.clone(),
+                previous,
+            }));
+        }
+
+        let Some(properties) = ctx.classes.find_property(class, key) else {
+            if has_schema && ctx.classes.find_handler(class, key).is_none() {
+                lints.push(Box::new(PropertyLint::Unknown {
+                    class: class.to_string(),
+                    key: key.to_string(),
+                    range,
+                }));
+            }
+            continue;
+        };
+
+        let (property, depth) = properties.lookup(array);
+        let key = generate_function_name(&array[..depth]);
+        let values = &array[depth..];
+        if values.len() < property.min_args || values.len() > property.max_args
+        {
+            lints.push(Box::new(PropertyLint::WrongCount {
+                key,
+                range: entry.location.clone(),
+                min: property.min_args,
+                max: property.max_args,
+            }));
+            continue;
+        }
+        for (i, value) in values.iter().enumerate() {
+            let Some(expected) = property.param(i) else {
+                break;
+            };
+            if !expected.accepts(value) {
+                lints.push(Box::new(PropertyLint::WrongKind {
+                    key: key.clone(),
+                    expected,
+                    found: describe(value),
+                    range: value.location.clone(),
+                }));
+            } else {
+                lint_asset(lints, expected, value, ctx.assets);
+            }
+        }
+    }
+}
+
 // functions
 
 enum FunctionArgLint {
-    TooManyArgs(String, Range<usize>),
-    NotEnoughArgs(String, Range<usize>),
+    // the last field names the overload the call was checked against
+    TooManyArgs(String, Range<usize>, Option<String>),
+    NotEnoughArgs(String, Range<usize>, Option<String>),
+    WrongKind {
+        name: String,
+        position: usize,
+        expected: ArgKind,
+        found: &'static str,
+        range: Range<usize>,
+        overload: Option<String>,
+    },
+    // the signatures of every overload
+    NoOverload(String, Range<usize>, Vec<String>),
 }
 
 impl Lint for FunctionArgLint {
     fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
-        match self {
-            Self::TooManyArgs(name, range) => Diagnostic::error()
+        let notes = match self {
+            Self::TooManyArgs(.., overload)
+            | Self::NotEnoughArgs(.., overload)
+            | Self::WrongKind { overload, .. } => overload
+                .iter()
+                .map(|overload| format!("expected the overload `{overload}`"))
+                .collect(),
+            Self::NoOverload(name, _, signatures) => signatures
+                .iter()
+                .map(|signature| format!("overload `{name} : {signature}`"))
+                .collect(),
+        };
+
+        let diag = match self {
+            Self::TooManyArgs(name, range, _) => Diagnostic::error()
                 .with_message(format!(
                     "calling `{name}` with too many arguments"
                 ))
                 .with_labels(vec![Label::primary(id, range.clone())
                     .with_message("too many arguments")]),
-            Self::NotEnoughArgs(name, range) => Diagnostic::error()
+            Self::NotEnoughArgs(name, range, _) => Diagnostic::error()
                 .with_message(format!(
                     "calling `{name}` with too few arguments"
                 ))
                 .with_labels(vec![Label::primary(id, range.clone())
                     .with_message("not enough arguments")]),
+            Self::WrongKind {
+                name,
+                position,
+                expected,
+                found,
+                range,
+                ..
+            } => Diagnostic::error()
+                .with_message(format!(
+                    "argument {position} of `{name}` should be {}",
+                    expected.article()
+                ))
+                .with_labels(vec![Label::primary(id, range.clone())
+                    .with_message(format!(
+                        "expected {expected}, found {found}"
+                    ))]),
+            Self::NoOverload(name, range, _) => Diagnostic::error()
+                .with_message(format!("no overload of `{name}` matches"))
+                .with_labels(vec![Label::primary(id, range.clone())
+                    .with_message("matches no overload")]),
+        };
+        diag.with_notes(notes)
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Self::TooManyArgs(..) => "too-many-args",
+            Self::NotEnoughArgs(..) => "too-few-args",
+            Self::WrongKind { .. } => "wrong-arg-kind",
+            Self::NoOverload(..) => "no-matching-overload",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgKind {
+    Any,
+    Int,
+    Float,
+    Number,
+    Symbol,
+    String,
+    Variable,
+    Array,
+    Command,
+    Object,
+    Path,
+}
+
+impl ArgKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Number => "number",
+            Self::Symbol => "symbol",
+            Self::String => "string",
+            Self::Variable => "variable",
+            Self::Array => "array",
+            Self::Command => "command",
+            Self::Object => "object",
+            Self::Path => "path",
+        }
+    }
+
+    fn article(self) -> String {
+        match self {
+            Self::Any => "anything".to_string(),
+            Self::Int | Self::Array | Self::Object => format!("an {self}"),
+            _ => format!("a {self}"),
+        }
+    }
+
+    pub fn accepts(self, expr: &Expression) -> bool {
+        // these are only known once the script runs
+        let dynamic = matches!(
+            expr.value,
+            ExpressionValue::Variable(_)
+                | ExpressionValue::Command(_)
+                | ExpressionValue::Property(_)
+                | ExpressionValue::Unhandled
+        );
+        // symbols may be macros, which can expand to anything
+        let macro_like = matches!(expr.value, ExpressionValue::Symbol(_));
+
+        match self {
+            Self::Any => true,
+            Self::Int => {
+                dynamic
+                    || macro_like
+                    || matches!(expr.value, ExpressionValue::Integer(_))
+            }
+            Self::Float | Self::Number => {
+                dynamic
+                    || macro_like
+                    || matches!(
+                        expr.value,
+                        ExpressionValue::Integer(_) | ExpressionValue::Float(_)
+                    )
+            }
+            Self::Symbol => dynamic || macro_like,
+            Self::String => {
+                dynamic
+                    || macro_like
+                    || matches!(expr.value, ExpressionValue::String(_))
+            }
+            Self::Variable => {
+                matches!(expr.value, ExpressionValue::Variable(_))
+            }
+            Self::Array => {
+                dynamic
+                    || macro_like
+                    || matches!(expr.value, ExpressionValue::Array(_))
+            }
+            Self::Command => {
+                matches!(expr.value, ExpressionValue::Command(_))
+            }
+            Self::Object | Self::Path => {
+                dynamic
+                    || macro_like
+                    || matches!(expr.value, ExpressionValue::String(_))
+            }
         }
     }
 }
 
+impl ArgKind {
+    // whether the argument is written as exactly this kind, rather than
+    // something that might turn out to be one
+    fn is_exact(self, expr: &Expression) -> bool {
+        match self {
+            Self::Any => false,
+            Self::Int => matches!(expr.value, ExpressionValue::Integer(_)),
+            Self::Float => matches!(expr.value, ExpressionValue::Float(_)),
+            Self::Number => matches!(
+                expr.value,
+                ExpressionValue::Integer(_) | ExpressionValue::Float(_)
+            ),
+            Self::Symbol => matches!(expr.value, ExpressionValue::Symbol(_)),
+            Self::String | Self::Object | Self::Path => {
+                matches!(expr.value, ExpressionValue::String(_))
+            }
+            Self::Variable => {
+                matches!(expr.value, ExpressionValue::Variable(_))
+            }
+            Self::Array => matches!(expr.value, ExpressionValue::Array(_)),
+            Self::Command => matches!(expr.value, ExpressionValue::Command(_)),
+        }
+    }
+}
+
+impl fmt::Display for ArgKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ArgKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "any" => Self::Any,
+            "int" => Self::Int,
+            "float" => Self::Float,
+            "number" => Self::Number,
+            "symbol" => Self::Symbol,
+            "string" => Self::String,
+            "variable" => Self::Variable,
+            "array" => Self::Array,
+            "command" => Self::Command,
+            "object" => Self::Object,
+            "path" => Self::Path,
+            _ => return Err(format!("unknown argument kind `{s}`")),
+        })
+    }
+}
+
+fn describe(expr: &Expression) -> &'static str {
+    match expr.value {
+        ExpressionValue::Integer(_) => "an int",
+        ExpressionValue::Float(_) => "a float",
+        ExpressionValue::String(_) => "a string",
+        ExpressionValue::Symbol(_) => "a symbol",
+        ExpressionValue::Variable(_) => "a variable",
+        ExpressionValue::Unhandled => "kDataUnhandled",
+        ExpressionValue::Array(_) => "an array",
+        ExpressionValue::Command(_) => "a command",
+        ExpressionValue::Property(_) => "a property",
+        _ => "a directive",
+    }
+}
+
 pub struct Function {
     pub min_args: usize,
     pub max_args: usize,
+    // expected kind of each argument. when `max_args` is unbounded the last
+    // kind applies to all remaining arguments
+    pub params: Vec<ArgKind>,
+    // values that arguments must be written as for an overload to be
+    // picked, by position
+    pub literals: Vec<(usize, String)>,
+    // signatures set with `#overload`, which calls are checked against
+    // instead of this one
+    pub overloads: Vec<Function>,
     pub children: HashMap<String, Function>,
 }
 
@@ -97,16 +830,127 @@ impl Default for Function {
         Self {
             min_args: 0,
             max_args: usize::MAX,
+            params: Vec::new(),
+            literals: Vec::new(),
+            overloads: Vec::new(),
             children: HashMap::default(),
         }
     }
 }
 
+// whether an argument is written as a literal from the config
+fn is_literal(literal: &str, arg: &Expression) -> bool {
+    match &arg.value {
+        ExpressionValue::Integer(value) => literal.parse() == Ok(*value),
+        ExpressionValue::Float(value) => literal.parse() == Ok(*value),
+        ExpressionValue::String(value) => {
+            literal.strip_prefix('"').and_then(|l| l.strip_suffix('"'))
+                == Some(value.as_ref())
+        }
+        ExpressionValue::Symbol(value) => literal == value.as_ref(),
+        _ => false,
+    }
+}
+
 impl Function {
+    /// The entry for a command and how many of its leading symbols named
+    /// it, with the overload its arguments pick when it has any.
     pub fn lookup(&self, stmt: &[Expression]) -> (&Function, usize) {
+        let (func, depth) = self.find(stmt);
+        (func.select(&stmt[depth..]).unwrap_or(func), depth)
+    }
+
+    /// The entry for a command, without picking an overload.
+    pub fn find(&self, stmt: &[Expression]) -> (&Function, usize) {
         self.lookup_inner(stmt, 0)
     }
 
+    /// The overload that `args` match the most literals of, then that they
+    /// fit, then whose kinds they match most exactly. Overloads whose
+    /// literals they can't match are never picked, so `None` means none
+    /// of them could be.
+    pub fn select(&self, args: &[Expression]) -> Option<&Function> {
+        if self.overloads.is_empty() {
+            return Some(self);
+        }
+
+        let mut best = None;
+        for overload in &self.overloads {
+            let Some(score) = overload.score(args) else {
+                continue;
+            };
+            // ties go to the overload defined first
+            if best.as_ref().is_some_and(|(best, _)| score <= *best) {
+                continue;
+            }
+            best = Some((score, overload));
+        }
+        best.map(|(_, overload)| overload)
+    }
+
+    fn score(&self, args: &[Expression]) -> Option<(usize, bool, usize)> {
+        let mut literals = 0;
+        for (position, literal) in &self.literals {
+            let arg = args.get(*position)?;
+            if is_literal(literal, arg) {
+                literals += 1;
+            } else if !matches!(
+                arg.value,
+                ExpressionValue::Variable(_)
+                    | ExpressionValue::Command(_)
+                    | ExpressionValue::Property(_)
+            ) {
+                // values only known once the script runs could be anything
+                return None;
+            }
+        }
+
+        let kinds = args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| self.param(i).map(|kind| (kind, arg)));
+        let fits = args.len() >= self.min_args
+            && args.len() <= self.max_args
+            && kinds.clone().flatten().all(|(kind, arg)| kind.accepts(arg));
+        let exact = kinds
+            .flatten()
+            .filter(|(kind, arg)| kind.is_exact(arg))
+            .count();
+        Some((literals, fits, exact))
+    }
+
+    /// Parameters as written in `.dtacheckfns`, or the argument counts when
+    /// there are no typed parameters.
+    pub fn signature(&self) -> String {
+        if self.params.is_empty() {
+            return match (self.min_args, self.max_args) {
+                (min, usize::MAX) => format!("{min} or more arguments"),
+                (1, 1) => "1 argument".to_string(),
+                (min, max) if min == max => format!("{min} arguments"),
+                (min, max) => format!("{min} to {max} arguments"),
+            };
+        }
+
+        let last = self.params.len() - 1;
+        self.params
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                let literal = self.literals.iter().find(|(p, _)| *p == i);
+                if let Some((_, literal)) = literal {
+                    format!("={literal}")
+                } else if i == last && self.max_args == usize::MAX {
+                    format!("{kind}...")
+                } else if i >= self.min_args {
+                    format!("{kind}?")
+                } else {
+                    kind.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     fn lookup_inner(
         &self,
         stmt: &[Expression],
@@ -131,21 +975,41 @@ impl Function {
         func.lookup_inner(&stmt[1..], depth + 1)
     }
 
-    pub fn insert(&mut self, path: &[&str], min_args: usize, max_args: usize) {
-        if path.is_empty() {
-            self.min_args = min_args;
-            self.max_args = max_args;
-            return;
+    pub fn param(&self, position: usize) -> Option<ArgKind> {
+        match self.params.get(position) {
+            Some(kind) => Some(*kind),
+            None if self.max_args == usize::MAX => self.params.last().copied(),
+            None => None,
         }
+    }
 
-        if let Some(child) = self.children.get_mut(path[0]) {
-            child.insert(&path[1..], min_args, max_args);
-        } else {
-            let mut child = Function::default();
-            child.insert(&path[1..], min_args, max_args);
-            self.children.insert(path[0].to_string(), child);
+    fn entry(&mut self, path: &[&str]) -> &mut Function {
+        match path.split_first() {
+            Some((name, rest)) => self
+                .children
+                .entry(name.to_string())
+                .or_default()
+                .entry(rest),
+            None => self,
         }
     }
+
+    pub fn insert(
+        &mut self,
+        path: &[&str],
+        min_args: usize,
+        max_args: usize,
+        params: Vec<ArgKind>,
+    ) {
+        let func = self.entry(path);
+        func.min_args = min_args;
+        func.max_args = max_args;
+        func.params = params;
+    }
+
+    pub fn set_overloads(&mut self, path: &[&str], overloads: Vec<Function>) {
+        self.entry(path).overloads = overloads;
+    }
 }
 
 fn lint_fn_args(
@@ -153,13 +1017,67 @@ fn lint_fn_args(
     stmt: &[Expression],
     span: Range<usize>,
     funcs: &Function,
+    assets: Option<Assets>,
 ) {
-    let (func, depth) = funcs.lookup(stmt);
+    let (node, depth) = funcs.find(stmt);
     let name = generate_function_name(&stmt[..depth]);
-    if stmt.len() > func.max_args + depth {
-        lints.push(Box::new(FunctionArgLint::TooManyArgs(name, span)));
+    let Some(func) = node.select(&stmt[depth..]) else {
+        let signatures =
+            node.overloads.iter().map(Function::signature).collect();
+        lints.push(Box::new(FunctionArgLint::NoOverload(
+            name, span, signatures,
+        )));
+        return;
+    };
+    let overload = (!node.overloads.is_empty())
+        .then(|| format!("{name} : {}", func.signature()));
+    if stmt.len() > func.max_args.saturating_add(depth) {
+        lints
+            .push(Box::new(FunctionArgLint::TooManyArgs(name, span, overload)));
     } else if stmt.len() < func.min_args + depth {
-        lints.push(Box::new(FunctionArgLint::NotEnoughArgs(name, span)));
+        lints.push(Box::new(FunctionArgLint::NotEnoughArgs(
+            name, span, overload,
+        )));
+    } else {
+        for (i, arg) in stmt[depth..].iter().enumerate() {
+            let Some(expected) = func.param(i) else {
+                break;
+            };
+
+            if !expected.accepts(arg) {
+                lints.push(Box::new(FunctionArgLint::WrongKind {
+                    name: name.clone(),
+                    position: i + 1,
+                    expected,
+                    found: describe(arg),
+                    range: arg.location.clone(),
+                    overload: overload.clone(),
+                }));
+            } else {
+                lint_asset(lints, expected, arg, assets);
+            }
+        }
+    }
+}
+
+// string literals passed as a `path` should name a file. an empty string
+// usually means no file at all
+fn lint_asset(
+    lints: &mut Vec<Box<dyn Lint>>,
+    expected: ArgKind,
+    arg: &Expression,
+    assets: Option<Assets>,
+) {
+    let (ArgKind::Path, Some(assets), ExpressionValue::String(path)) =
+        (expected, assets, &arg.value)
+    else {
+        return;
+    };
+    if !path.is_empty() && !assets.exists(path) {
+        lints.push(Box::new(MissingAsset {
+            path: path.to_string(),
+            range: arg.location.clone(),
+        }));
     }
 }
 
@@ -177,43 +1095,329 @@ fn generate_function_name(stmt: &[Expression]) -> String {
 
 // switch fallthough
 
-struct SwitchFallthroughLint(Range<usize>, Range<usize>);
+enum SwitchLint {
+    MissingFallthrough(String, Range<usize>, Range<usize>),
+    UnreachableCase(String, Range<usize>, Range<usize>),
+    EmptyCase(String, Range<usize>),
+}
 
-impl Lint for SwitchFallthroughLint {
+impl Lint for SwitchLint {
     fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
-        Diagnostic::warning()
-            .with_message("missing fallthrough for switch")
-            .with_labels(vec![
-                Label::primary(id, self.0.clone()),
-                Label::secondary(id, self.1.clone())
-                    .with_message("consider adding a fallthrough node here"),
-            ])
+        match self {
+            Self::MissingFallthrough(name, span, pos) => Diagnostic::warning()
+                .with_message(format!("missing fallthrough for {name}"))
+                .with_labels(vec![
+                    Label::primary(id, span.clone()),
+                    Label::secondary(id, pos.clone()).with_message(
+                        "consider adding a fallthrough node here",
+                    ),
+                ]),
+            Self::UnreachableCase(name, case, fallthrough) => {
+                Diagnostic::warning()
+                    .with_message(format!("unreachable {name} case"))
+                    .with_labels(vec![
+                        Label::primary(id, case.clone())
+                            .with_message("this case is never checked"),
+                        Label::secondary(id, fallthrough.clone()).with_message(
+                            "because this fallthrough comes first",
+                        ),
+                    ])
+            }
+            Self::EmptyCase(name, case) => Diagnostic::error()
+                .with_message(format!("empty {name} case"))
+                .with_labels(vec![Label::primary(id, case.clone())
+                    .with_message("a case needs a value to match on")]),
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Self::MissingFallthrough(..) => "missing-fallthrough",
+            Self::UnreachableCase(..) => "unreachable-case",
+            Self::EmptyCase(..) => "empty-case",
+        }
     }
 }
 
+// `switch` and `cond` take a list of `(value body...)` cases. the first
+// node that isn't an array is the fallthrough, which is evaluated when no
+// case matches, so it has to come last
 fn lint_switch_fallthrough(
     lints: &mut Vec<Box<dyn Lint>>,
     stmt: &[Expression],
     span: Range<usize>,
 ) {
-    if stmt.is_empty() {
-        return;
-    }
-
-    let ExpressionValue::Symbol(ref sym) = stmt[0].value else {
+    let Some(ExpressionValue::Symbol(ref sym)) = stmt.first().map(|e| &e.value)
+    else {
         return;
     };
 
-    if sym != "switch" {
-        return;
-    }
+    let name: &str = sym.as_ref();
+    let first_case = match name {
+        "switch" => 2,
+        "cond" => 1,
+        _ => return,
+    };
 
-    let Some(last_node) = stmt.last() else {
+    let Some(cases) = stmt.get(first_case..) else {
         return;
     };
 
-    if matches!(last_node.value, ExpressionValue::Array(_)) {
+    let mut fallthrough: Option<Range<usize>> = None;
+    for case in cases {
+        if let Some(fallthrough) = &fallthrough {
+            lints.push(Box::new(SwitchLint::UnreachableCase(
+                name.to_string(),
+                case.location.clone(),
+                fallthrough.clone(),
+            )));
+            continue;
+        }
+
+        match &case.value {
+            ExpressionValue::Array(body) if body.is_empty() => {
+                lints.push(Box::new(SwitchLint::EmptyCase(
+                    name.to_string(),
+                    case.location.clone(),
+                )));
+            }
+            ExpressionValue::Array(_) => (),
+            _ => fallthrough = Some(case.location.clone()),
+        }
+    }
+
+    if fallthrough.is_none() && !cases.is_empty() {
         let pos = span.end - 1;
-        lints.push(Box::new(SwitchFallthroughLint(span, pos..pos)))
+        lints.push(Box::new(SwitchLint::MissingFallthrough(
+            name.to_string(),
+            span,
+            pos..pos,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use arson_parse::ParseOptions;
+
+    use super::*;
+    use crate::config::Config;
+
+    fn config(text: &str) -> Config {
+        let config = Config::parse(Path::new("fns"), text);
+        assert!(config.diagnostics.is_empty(), "config has errors");
+        config
+    }
+
+    fn parse(text: &str) -> Vec<Expression<'_>> {
+        let options = ParseOptions {
+            include_comments: false,
+        };
+        arson_parse::parse_text(text, options).unwrap()
+    }
+
+    // lints a script against a config, as dtacheck would by default
+    fn lint(config_text: &str, text: &str) -> Vec<Diagnostic<usize>> {
+        let config = config(config_text);
+        let ast = parse(text);
+        let lints = lint_file(
+            &ast,
+            &config.funcs,
+            &config.classes,
+            &config.objects,
+            None,
+        );
+        to_diagnostics(&lints, 0, &config.levels, &Suppressions::default())
+    }
+
+    fn ids(diagnostics: &[Diagnostic<usize>]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .filter_map(|diag| diag.code.as_deref())
+            .collect()
+    }
+
+    fn primary(diag: &Diagnostic<usize>) -> Range<usize> {
+        diag.labels[0].range.clone()
+    }
+
+    #[test]
+    fn switch_without_default_is_missing_fallthrough() {
+        let text = "{switch $x (1 {a}) (2 {b})}";
+        let diagnostics = lint("", text);
+        assert_eq!(ids(&diagnostics), ["missing-fallthrough"]);
+        assert_eq!(primary(&diagnostics[0]), 0..text.len());
+
+        let diagnostics = lint("", "{cond ({== $x 1} {a})}");
+        assert_eq!(ids(&diagnostics), ["missing-fallthrough"]);
+        assert!(lint("", "{switch $x (1 {a}) {b}}").is_empty());
+    }
+
+    #[test]
+    fn cases_after_the_default_are_unreachable() {
+        let text = "{switch $x (1 {a}) {b} (2 {c})}";
+        let diagnostics = lint("", text);
+        assert_eq!(ids(&diagnostics), ["unreachable-case"]);
+        let case = text.find("(2").unwrap();
+        assert_eq!(primary(&diagnostics[0]), case..case + "(2 {c})".len());
+    }
+
+    #[test]
+    fn empty_cases_are_reported() {
+        let text = "{cond () {b}}";
+        let diagnostics = lint("", text);
+        assert_eq!(ids(&diagnostics), ["empty-case"]);
+        assert_eq!(primary(&diagnostics[0]), 6..8);
+    }
+
+    #[test]
+    fn switch_lints_can_be_allowed() {
+        let text = "{switch $x (1 {a})}";
+        assert!(lint("#lint missing-fallthrough allow\n", text).is_empty());
+    }
+
+    // the signature of the overload a command picks
+    fn selected(config_text: &str, text: &str) -> Option<String> {
+        let config = config(config_text);
+        let ast = parse(text);
+        let ExpressionValue::Command(stmt) = &ast[0].value else {
+            panic!("expected a command");
+        };
+        let (node, depth) = config.funcs.find(stmt);
+        node.select(&stmt[depth..]).map(Function::signature)
+    }
+
+    const BEATMATCH: &str = "beatmatch : symbol int?\n\
+                             #overload beatmatch : =set int int\n\
+                             #overload beatmatch : =reset\n";
+
+    #[test]
+    fn literal_overloads_win() {
+        let set = Some("=set int int".to_string());
+        assert_eq!(selected(BEATMATCH, "{beatmatch set 1 2}"), set);
+        // a literal match beats an overload the arguments fit better
+        assert_eq!(selected(BEATMATCH, "{beatmatch set 1}"), set);
+        assert_eq!(
+            selected(BEATMATCH, "{beatmatch reset}"),
+            Some("=reset".to_string())
+        );
+        assert_eq!(
+            selected(BEATMATCH, "{beatmatch other 1}"),
+            Some("symbol int?".to_string())
+        );
+
+        let diagnostics = lint(BEATMATCH, "{beatmatch set 1}");
+        assert_eq!(ids(&diagnostics), ["too-few-args"]);
+        assert_eq!(
+            diagnostics[0].notes,
+            ["expected the overload `beatmatch : =set int int`"]
+        );
+    }
+
+    #[test]
+    fn exact_kinds_beat_loose_ones() {
+        let config = "#overload f : any\n#overload f : int\n";
+        assert_eq!(selected(config, "{f 1}"), Some("int".to_string()));
+        assert_eq!(selected(config, "{f \"s\"}"), Some("any".to_string()));
+    }
+
+    #[test]
+    fn ties_go_to_the_first_overload() {
+        let int_first = "#overload f : int\n#overload f : number\n";
+        let number_first = "#overload f : number\n#overload f : int\n";
+        for _ in 0..10 {
+            assert_eq!(selected(int_first, "{f $x}"), Some("int".to_string()));
+            assert_eq!(
+                selected(number_first, "{f $x}"),
+                Some("number".to_string())
+            );
+        }
+
+        // a plain signature comes first however it was ordered
+        let plain_last = "#overload f : number\nf : int\n";
+        assert_eq!(selected(plain_last, "{f $x}"), Some("int".to_string()));
+    }
+
+    #[test]
+    fn no_overload_is_reported_when_nothing_fits() {
+        let config = "#overload set_mode : =fast int\n\
+                      #overload set_mode : =slow float\n";
+        assert_eq!(selected(config, "{set_mode medium 1}"), None);
+
+        let text = "{set_mode medium 1}";
+        let diagnostics = lint(config, text);
+        assert_eq!(ids(&diagnostics), ["no-matching-overload"]);
+        assert_eq!(primary(&diagnostics[0]), 0..text.len());
+        assert_eq!(
+            diagnostics[0].notes,
+            [
+                "overload `set_mode : =fast int`",
+                "overload `set_mode : =slow float`",
+            ]
+        );
+    }
+
+    fn messages(diagnostics: &[Diagnostic<usize>]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .map(|diag| diag.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn wrong_kinds_point_at_the_argument() {
+        let config = "play : symbol int\n";
+        let text = "{play intro \"loud\"}";
+        let diagnostics = lint(config, text);
+        assert_eq!(ids(&diagnostics), ["wrong-arg-kind"]);
+        assert_eq!(
+            messages(&diagnostics),
+            ["argument 2 of `play` should be an int"]
+        );
+        assert_eq!(primary(&diagnostics[0]), 12..18);
+        assert_eq!(
+            diagnostics[0].labels[0].message,
+            "expected int, found a string"
+        );
+
+        // every mismatched argument is reported
+        let text = "{play 1 \"loud\"}";
+        let ranges = lint(config, text).iter().map(primary).collect::<Vec<_>>();
+        assert_eq!(ranges, [6..7, 8..14]);
+    }
+
+    #[test]
+    fn optional_params_may_be_left_out() {
+        let config = "fade : float int? symbol?\n";
+        assert!(lint(config, "{fade 1.0}").is_empty());
+        assert!(lint(config, "{fade 1.0 2 out}").is_empty());
+        assert_eq!(ids(&lint(config, "{fade}")), ["too-few-args"]);
+        assert_eq!(ids(&lint(config, "{fade 1.0 2 out 3}")), ["too-many-args"]);
+
+        // optional arguments that are given are still checked
+        let text = "{fade 1.0 \"out\"}";
+        let diagnostics = lint(config, text);
+        assert_eq!(ids(&diagnostics), ["wrong-arg-kind"]);
+        assert_eq!(primary(&diagnostics[0]), 10..15);
+    }
+
+    #[test]
+    fn variadic_params_take_any_number_of_arguments() {
+        let config = "print_all : symbol int...\n";
+        assert_eq!(ids(&lint(config, "{print_all}")), ["too-few-args"]);
+        assert!(lint(config, "{print_all a}").is_empty());
+        assert!(lint(config, "{print_all a 1 2 3 4 5}").is_empty());
+
+        // every extra argument is checked against the variadic kind
+        let text = "{print_all a 1 \"two\" 3}";
+        let diagnostics = lint(config, text);
+        assert_eq!(ids(&diagnostics), ["wrong-arg-kind"]);
+        assert_eq!(
+            messages(&diagnostics),
+            ["argument 3 of `print_all` should be an int"]
+        );
+        assert_eq!(primary(&diagnostics[0]), 15..20);
     }
 }
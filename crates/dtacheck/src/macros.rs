@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Label;
+
+use crate::linter::Lint;
+
+// the `#ifdef`s and `#ifndef`s a node sits inside, and which branch of each
+type Conditions = Vec<(String, bool)>;
+
+struct Definition {
+    name: String,
+    // body text with whitespace normalized, for comparing definitions
+    body: String,
+    file_id: usize,
+    range: Range<usize>,
+    conditions: Conditions,
+}
+
+struct Reference {
+    name: String,
+    file_id: usize,
+    range: Range<usize>,
+}
+
+// two definitions can both be active unless one sits in a branch that
+// excludes the other
+fn compatible(a: &Conditions, b: &Conditions) -> bool {
+    !a.iter().any(|(symbol, taken)| {
+        b.iter()
+            .any(|(other, other_taken)| symbol == other && taken != other_taken)
+    })
+}
+
+/// Whether a symbol follows the `ALL_CAPS` naming used for macros.
+pub fn is_macro_name(name: &str) -> bool {
+    name.len() > 1
+        && name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+enum MacroLint {
+    Undefined(String, Range<usize>),
+    Redefined {
+        name: String,
+        range: Range<usize>,
+        previous_file: usize,
+        previous: Range<usize>,
+    },
+    Unused(String, Range<usize>),
+}
+
+impl Lint for MacroLint {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        match self {
+            Self::Undefined(name, range) => Diagnostic::warning()
+                .with_message(format!("undefined macro `{name}`"))
+                .with_labels(vec![Label::primary(id, range.clone())])
+                .with_notes(vec![
+                    "symbols in ALL_CAPS are expected to be macros".to_string(),
+                ]),
+            Self::Redefined {
+                name,
+                range,
+                previous_file,
+                previous,
+            } => Diagnostic::warning()
+                .with_message(format!(
+                    "macro `{name}` is redefined with a different body"
+                ))
+                .with_labels(vec![
+                    Label::primary(id, range.clone()),
+                    Label::secondary(*previous_file, previous.clone())
+                        .with_message("previously defined here"),
+                ]),
+            Self::Unused(name, range) => Diagnostic::warning()
+                .with_message(format!("macro `{name}` is never used"))
+                .with_labels(vec![Label::primary(id, range.clone())]),
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Undefined(..) => "undefined-macro",
+            Self::Redefined { .. } => "macro-redefined",
+            Self::Unused(..) => "unused-macro",
+        }
+    }
+}
+
+/// Every macro defined and referenced across the files being linted.
+#[derive(Default)]
+pub struct MacroTable {
+    definitions: Vec<Definition>,
+    references: Vec<Reference>,
+    // names used in conditionals or `#undef`, which count as uses but are
+    // not expected to be defined
+    mentioned: HashSet<String>,
+    undefined: HashSet<String>,
+}
+
+impl MacroTable {
+    /// Records the definitions and references in a file, including those
+    /// in every branch of its conditionals.
+    pub fn add_file(&mut self, file_id: usize, text: &str, ast: &[Expression]) {
+        self.collect(file_id, text, ast, &mut Vec::new());
+    }
+
+    fn collect(
+        &mut self,
+        file_id: usize,
+        text: &str,
+        ast: &[Expression],
+        conditions: &mut Conditions,
+    ) {
+        for node in ast {
+            match &node.value {
+                ExpressionValue::Symbol(sym) => {
+                    let name: &str = sym.as_ref();
+                    self.references.push(Reference {
+                        name: name.to_string(),
+                        file_id,
+                        range: node.location.clone(),
+                    });
+                }
+                ExpressionValue::Array(array)
+                | ExpressionValue::Command(array)
+                | ExpressionValue::Property(array) => {
+                    self.collect(file_id, text, array, conditions)
+                }
+                ExpressionValue::Define(name, array) => {
+                    let body = text
+                        .get(array.location.clone())
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    self.definitions.push(Definition {
+                        name: name.text.to_string(),
+                        body,
+                        file_id,
+                        range: name.location.clone(),
+                        conditions: conditions.clone(),
+                    });
+                    self.collect(file_id, text, &array.exprs, conditions);
+                }
+                ExpressionValue::Undefine(name) => {
+                    self.mentioned.insert(name.text.to_string());
+                    self.undefined.insert(name.text.to_string());
+                }
+                ExpressionValue::Autorun(array) => {
+                    self.collect(file_id, text, &array.exprs, conditions)
+                }
+                ExpressionValue::Conditional {
+                    is_positive,
+                    symbol,
+                    true_branch,
+                    false_branch,
+                } => {
+                    self.mentioned.insert(symbol.text.to_string());
+
+                    let symbol = symbol.text.to_string();
+                    conditions.push((symbol.clone(), *is_positive));
+                    self.collect(file_id, text, &true_branch.exprs, conditions);
+                    conditions.pop();
+
+                    if let Some(false_branch) = false_branch {
+                        conditions.push((symbol, !is_positive));
+                        self.collect(
+                            file_id,
+                            text,
+                            &false_branch.exprs,
+                            conditions,
+                        );
+                        conditions.pop();
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Lints for undefined, redefined and unused macros, with the file id
+    /// each applies to. `external` names macros defined outside the linted
+    /// files, such as by the engine.
+    pub fn lints(
+        &self,
+        external: &HashSet<String>,
+    ) -> Vec<(usize, Box<dyn Lint>)> {
+        let mut lints: Vec<(usize, Box<dyn Lint>)> = Vec::new();
+        let defined = self
+            .definitions
+            .iter()
+            .map(|def| def.name.as_str())
+            .collect::<HashSet<_>>();
+
+        for reference in &self.references {
+            let name = reference.name.as_str();
+            if is_macro_name(name)
+                && !defined.contains(name)
+                && !external.contains(name)
+            {
+                lints.push((
+                    reference.file_id,
+                    Box::new(MacroLint::Undefined(
+                        reference.name.clone(),
+                        reference.range.clone(),
+                    )),
+                ));
+            }
+        }
+
+        // earlier definitions of each name, so each definition is only
+        // compared with those of the same macro
+        let mut by_name: HashMap<&str, Vec<&Definition>> = HashMap::new();
+        for def in &self.definitions {
+            if self.undefined.contains(&def.name) {
+                continue;
+            }
+
+            let earlier = by_name.entry(def.name.as_str()).or_default();
+            let previous = earlier.iter().find(|previous| {
+                previous.body != def.body
+                    && compatible(&previous.conditions, &def.conditions)
+            });
+            if let Some(previous) = previous {
+                lints.push((
+                    def.file_id,
+                    Box::new(MacroLint::Redefined {
+                        name: def.name.clone(),
+                        range: def.range.clone(),
+                        previous_file: previous.file_id,
+                        previous: previous.range.clone(),
+                    }),
+                ));
+            }
+            earlier.push(def);
+        }
+
+        let used = self
+            .references
+            .iter()
+            .map(|reference| reference.name.as_str())
+            .chain(self.mentioned.iter().map(String::as_str))
+            .collect::<HashSet<_>>();
+        let mut reported = HashSet::new();
+        for def in &self.definitions {
+            if !used.contains(def.name.as_str())
+                && reported.insert(def.name.as_str())
+            {
+                lints.push((
+                    def.file_id,
+                    Box::new(MacroLint::Unused(
+                        def.name.clone(),
+                        def.range.clone(),
+                    )),
+                ));
+            }
+        }
+
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arson_parse::ParseOptions;
+
+    use super::*;
+
+    // the id and primary range of each lint over a set of files
+    fn lints(
+        files: &[&str],
+        external: &[&str],
+    ) -> Vec<(&'static str, usize, Range<usize>)> {
+        let mut table = MacroTable::default();
+        for (file_id, text) in files.iter().enumerate() {
+            let options = ParseOptions {
+                include_comments: false,
+            };
+            let ast = arson_parse::parse_text(text, options).unwrap();
+            table.add_file(file_id, text, &ast);
+        }
+        let external = external.iter().map(|name| name.to_string()).collect();
+        table
+            .lints(&external)
+            .into_iter()
+            .map(|(file_id, lint)| {
+                let diag = lint.to_codespan(file_id);
+                (lint.id(), file_id, diag.labels[0].range.clone())
+            })
+            .collect()
+    }
+
+    fn range(text: &str, needle: &str) -> Range<usize> {
+        let start = text.find(needle).unwrap();
+        start..start + needle.len()
+    }
+
+    #[test]
+    fn all_caps_symbols_must_be_defined() {
+        let text = "#define SONG (1)\n(SONG MISSING Mixed X lower ENGINE)\n";
+        let found = lints(&[text], &["ENGINE"]);
+        assert_eq!(found, [("undefined-macro", 0, range(text, "MISSING"))]);
+    }
+
+    #[test]
+    fn conditionals_and_undefs_count_as_mentions() {
+        let text = "#ifdef HX_XBOX\n(a)\n#endif\n#undef OTHER\n";
+        assert!(lints(&[text], &[]).is_empty());
+    }
+
+    #[test]
+    fn redefinitions_with_a_different_body_are_reported() {
+        let first = "#define SONG (1)\n(SONG)\n";
+        let second = "#define SONG (2)\n";
+        let found = lints(&[first, second], &[]);
+        assert_eq!(found, [("macro-redefined", 1, range(second, "SONG"))]);
+
+        let same = "#define SONG (1)\n";
+        assert!(lints(&[first, same], &[]).is_empty());
+    }
+
+    #[test]
+    fn definitions_in_exclusive_branches_are_not_redefinitions() {
+        let text = "#ifdef HX_XBOX\n#define SONG (1)\n#else\n\
+                    #define SONG (2)\n#endif\n(SONG)\n";
+        assert!(lints(&[text], &[]).is_empty());
+
+        let nested = "#ifdef HX_XBOX\n#define SONG (1)\n#endif\n\
+                      #define SONG (2)\n(SONG)\n";
+        let found = lints(&[nested], &[]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "macro-redefined");
+    }
+
+    #[test]
+    fn undefined_macros_may_be_redefined() {
+        let text = "#define SONG (1)\n#undef SONG\n#define SONG (2)\n(SONG)\n";
+        assert!(lints(&[text], &[]).is_empty());
+    }
+}
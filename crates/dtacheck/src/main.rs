@@ -1,86 +1,497 @@
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::error::Error;
 use std::fs;
+use std::fs::File;
 use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitCode;
 
 use arson_parse::reporting as codespan_reporting;
 use arson_parse::ParseOptions;
 use clap::Parser as ClapParser;
-use codespan_reporting::files::SimpleFiles;
+use clap::Subcommand;
+use clap::ValueEnum;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Severity;
 use codespan_reporting::term;
 use codespan_reporting::term::termcolor::ColorChoice;
 use codespan_reporting::term::termcolor::StandardStream;
 use codespan_reporting::term::Chars;
-use dtacheck::linter::lint_file;
-use dtacheck::linter::Function;
+use dtacheck::baseline::Baseline;
+use dtacheck::check::check;
+use dtacheck::config::Config;
+use dtacheck::encoding;
+use dtacheck::encoding::Decoded;
+use dtacheck::encoding::Encoding;
+use dtacheck::infer::Inference;
+use dtacheck::output;
+use dtacheck::preprocess::Variant;
+use dtacheck::preprocess::PLATFORMS;
+use dtacheck::program::Program;
+use milo::ark::ArkParts;
+use rayon::prelude::*;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Human readable diagnostics on stderr
+    Text,
+    /// A JSON array of diagnostics on stdout
+    Json,
+    /// A SARIF log on stdout
+    Sarif,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write a draft config from the commands used in existing scripts
+    Infer(InferArgs),
+}
+
+#[derive(clap::Args)]
+struct InferArgs {
+    /// .dta or .dtb files, archives, or directories containing any of them
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// File to write the config to instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
 
 #[derive(ClapParser)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    file: PathBuf,
-    config: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// .dta or .dtb files, archives, or directories containing any of them
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    #[arg(required = true)]
+    config: Option<PathBuf>,
+    /// How to report diagnostics
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Fail when any warnings are emitted, not just errors
+    #[arg(long)]
+    deny_warnings: bool,
+    /// File to touch when linting succeeds, for use as a build stamp
+    #[arg(long)]
+    stamp: Option<PathBuf>,
+    /// Write a depfile listing the config, all included files and the files
+    /// that `path` arguments name
+    #[arg(long, requires = "stamp")]
+    depfile: Option<PathBuf>,
+    /// Directory to look for included files in when they aren't found next
+    /// to the file including them, usually the game's DTA root. Files
+    /// passed as `path` arguments are also checked to exist under it
+    #[arg(long)]
+    root: Option<PathBuf>,
+    /// Encoding of .dta files, utf-8 or latin-1. Guessed for each file when
+    /// not given
+    #[arg(long)]
+    encoding: Option<Encoding>,
+    /// Macro to treat as defined when resolving `#ifdef`s
+    #[arg(short = 'D', long = "define", value_name = "MACRO")]
+    defines: Vec<String>,
+    /// Lint once per platform (xbox, ps3, wii or all), resolving `#ifdef`s
+    /// with the platform's macro defined
+    #[arg(
+        long = "platform",
+        value_name = "PLATFORM",
+        value_parser = parse_platform
+    )]
+    platforms: Vec<String>,
+    /// Only report diagnostics that aren't in this baseline
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+    /// Record the current diagnostics as a baseline instead of reporting
+    /// them
+    #[arg(long, value_name = "FILE", conflicts_with = "baseline")]
+    write_baseline: Option<PathBuf>,
 }
 
-fn load_funcs(path: &Path) -> Function {
-    let file_contents = fs::read_to_string(path).unwrap();
-    let mut tree = Function::default();
+fn parse_platform(name: &str) -> Result<String, String> {
+    if name == "all" || PLATFORMS.iter().any(|(p, _)| *p == name) {
+        Ok(name.to_string())
+    } else {
+        let names = PLATFORMS.iter().map(|(p, _)| *p).collect::<Vec<_>>();
+        Err(format!("expected one of {} or all", names.join(", ")))
+    }
+}
 
-    for line in file_contents.lines() {
-        if line.starts_with('#') {
-            continue;
+// with no platforms or macros given, conditionals are left unresolved and
+// commands containing them are skipped
+fn variants(args: &Args) -> Vec<Variant> {
+    let defines = args.defines.iter().cloned().collect::<HashSet<_>>();
+    if args.platforms.is_empty() {
+        if defines.is_empty() {
+            return Vec::new();
         }
+        return vec![Variant {
+            name: String::new(),
+            defines,
+        }];
+    }
+
+    let all = args.platforms.iter().any(|p| p == "all");
+    PLATFORMS
+        .iter()
+        .filter(|(name, _)| all || args.platforms.iter().any(|p| p == name))
+        .map(|(name, define)| {
+            let mut defines = defines.clone();
+            defines.insert(define.to_string());
+            Variant {
+                name: name.to_string(),
+                defines,
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Summary {
+    errors: usize,
+    warnings: usize,
+}
 
-        let tokens = line.split_whitespace().collect::<Vec<_>>();
-        let len = tokens.len();
-        if len < 3 {
-            continue;
+impl Summary {
+    fn record(&mut self, diag: &Diagnostic<usize>) {
+        match diag.severity {
+            Severity::Bug | Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Note | Severity::Help => (),
         }
+    }
 
-        let max_args = tokens[len - 1].parse::<usize>().unwrap();
-        let min_args = tokens[len - 2].parse::<usize>().unwrap();
+    fn failed(&self, deny_warnings: bool) -> bool {
+        self.errors > 0 || (deny_warnings && self.warnings > 0)
+    }
+}
 
-        tree.insert(&tokens[0..len - 2], min_args, max_args);
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
     }
+}
 
-    tree
+struct Source {
+    name: String,
+    decoded: Decoded,
+    // only sources read from disk can follow their includes
+    path: Option<PathBuf>,
 }
 
-fn main() {
-    let args = Args::parse();
-    let file_contents = &fs::read(&args.file).unwrap();
-    let data = String::from_utf8_lossy(file_contents).clone();
-    let funcs = load_funcs(&args.config);
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension().is_some_and(|ext| {
+        extensions.iter().any(|e| ext.eq_ignore_ascii_case(e))
+    })
+}
+
+fn is_dtb(path: &Path) -> bool {
+    has_extension(path, &["dtb"])
+}
+
+fn decode_dtb(name: String, data: &[u8]) -> Result<Source, Box<dyn Error>> {
+    let Some((plain, _)) = dtb::crypt::decrypt_any(data) else {
+        return Err(format!("{name}: not a recognized DTB").into());
+    };
+    let text =
+        dtb::decode::decode(&plain).map_err(|e| format!("{name}: {e}"))?;
+    // strings in DTBs are decoded as Windows-1252, like Latin-1 files
+    Ok(Source {
+        name,
+        decoded: Decoded {
+            text,
+            encoding: Encoding::Latin1,
+            mixed: None,
+        },
+        path: None,
+    })
+}
+
+// gathers the sources to lint. archives and entries in them that can't be
+// read are skipped with a warning, so the rest can still be linted
+#[derive(Default)]
+struct Collector {
+    sources: Vec<Source>,
+    warnings: Vec<String>,
+    encoding: Option<Encoding>,
+}
+
+impl Collector {
+    fn add(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if path.is_dir() {
+            self.add_dir(path)?;
+        } else if is_dtb(path) {
+            let data = fs::read(path)?;
+            self.add_dtb(path.display().to_string(), &data);
+        } else if has_extension(path, &["ark", "hdr"]) {
+            if let Err(e) = self.add_archive(path) {
+                self.warnings.push(format!("{}: {e}", path.display()));
+            }
+        } else {
+            let file_contents = fs::read(path)?;
+            self.sources.push(Source {
+                name: path.display().to_string(),
+                decoded: encoding::decode(&file_contents, self.encoding),
+                path: Some(path.to_path_buf()),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn add_dir(&mut self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        let mut entries = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+
+        // `name_0.ark` and so on next to a `name.hdr` are its parts, and are
+        // read through the header rather than on their own
+        let headers = entries
+            .iter()
+            .filter(|path| has_extension(path, &["hdr"]))
+            .filter_map(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .collect::<HashSet<_>>();
+        let is_part = |path: &Path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.rsplit_once('_'))
+                .is_some_and(|(stem, index)| {
+                    index.parse::<usize>().is_ok()
+                        && headers.contains(&stem.to_lowercase())
+                })
+        };
+
+        for path in entries {
+            if path.is_dir()
+                || is_dtb(&path)
+                || has_extension(&path, &["dta", "hdr"])
+                || (has_extension(&path, &["ark"]) && !is_part(&path))
+            {
+                self.add(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_dtb(&mut self, name: String, data: &[u8]) {
+        match decode_dtb(name, data) {
+            Ok(source) => self.sources.push(source),
+            Err(e) => self.warnings.push(e.to_string()),
+        }
+    }
+
+    fn add_archive(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut file = File::open(path)?;
+        let ark = milo::ark::load_ark_file(&mut file)?;
+        let parts = ArkParts::find(path)?;
 
-    let mut files = SimpleFiles::new();
-    let file_id = files.add(args.file.to_str().unwrap(), &data);
+        for entry in ark.entries()? {
+            if !is_dtb(Path::new(&entry.path)) {
+                continue;
+            }
 
-    let parse_options = ParseOptions {
+            let name = format!("{}:{}", path.display(), entry.path);
+            match milo::ark::read_entry(&parts, &entry) {
+                Ok(data) => self.add_dtb(name, &data),
+                Err(e) => self.warnings.push(format!("{name}: {e}")),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn escape_dep(path: &Path) -> String {
+    path.display()
+        .to_string()
+        .replace('$', "$$")
+        .replace('#', "\\#")
+        .replace(' ', "\\ ")
+}
+
+fn write_depfile(
+    depfile: &Path,
+    stamp: &Path,
+    deps: &BTreeSet<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let mut out = format!("{}:", escape_dep(stamp));
+    for dep in deps {
+        out.push_str(" \\\n  ");
+        out.push_str(&escape_dep(dep));
+    }
+    out.push('\n');
+    fs::write(depfile, out)?;
+    Ok(())
+}
+
+fn infer(args: &InferArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let mut collector = Collector::default();
+    for path in &args.files {
+        collector.add(path)?;
+    }
+    for warning in &collector.warnings {
+        eprintln!("{warning}, skipping");
+    }
+
+    let options = ParseOptions {
         include_comments: false,
     };
-    let (ast, diagnostics) = match arson_parse::parse_text(&data, parse_options) {
-        Ok(ast) => (ast, Vec::new()),
-        Err(error) => (Vec::new(), error.diagnostics),
+    let mut inference = Inference::default();
+    for source in &collector.sources {
+        match arson_parse::parse_text(&source.decoded.text, options) {
+            Ok(ast) => inference.add_file(&ast),
+            Err(_) => {
+                eprintln!("{}: could not be parsed, skipping", source.name)
+            }
+        }
+    }
+
+    let config = inference.config();
+    match &args.output {
+        Some(path) => fs::write(path, config)?,
+        None => print!("{config}"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let args = Args::parse();
+    if let Some(Command::Infer(infer_args)) = &args.command {
+        return infer(infer_args);
+    }
+
+    let mut collector = Collector {
+        encoding: args.encoding,
+        ..Collector::default()
     };
+    for path in &args.files {
+        collector.add(path)?;
+    }
+    let config_path = args
+        .config
+        .as_deref()
+        .expect("clap requires a config without a subcommand");
+    let config = Config::load(config_path).map_err(|e| {
+        format!("could not read `{}`: {e}", config_path.display())
+    })?;
+
+    let variants = variants(&args);
 
     let writer = StandardStream::stderr(ColorChoice::Auto);
-    let config = codespan_reporting::term::Config {
+    let term_config = codespan_reporting::term::Config {
         chars: Chars::ascii(),
         ..Default::default()
     };
 
-    for diag in diagnostics {
-        let _ = term::emit(
-            &mut writer.lock(),
-            &config,
-            &files,
-            &diag.to_codespan(file_id),
+    let programs = collector
+        .sources
+        .into_par_iter()
+        .map(|source| {
+            Program::load(
+                source.name,
+                source.path,
+                source.decoded,
+                args.root.as_deref(),
+                args.encoding,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut report = check(&config, &programs, &variants);
+    // sources that couldn't be read, which have no file to point at
+    let skipped = collector.warnings.into_iter().map(|warning| {
+        Diagnostic::warning()
+            .with_message(warning)
+            .with_notes(vec!["it was skipped".to_string()])
+    });
+    report.diagnostics.splice(0..0, skipped);
+    if let Some(path) = &args.write_baseline {
+        let baseline =
+            Baseline::from_diagnostics(&report.files, &report.diagnostics);
+        fs::write(path, format!("{:#}\n", baseline.to_json()))?;
+        eprintln!(
+            "wrote {} to `{}`",
+            plural(baseline.len(), "diagnostic"),
+            path.display()
         );
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(path) = &args.baseline {
+        let baseline = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Baseline::parse(&text))
+            .map_err(|e| {
+                format!("could not read baseline `{}`: {e}", path.display())
+            })?;
+        report.diagnostics = baseline.filter(&report.files, report.diagnostics);
     }
 
-    for diag in lint_file(&ast, &funcs) {
-        let _ = term::emit(
-            &mut writer.lock(),
-            &config,
-            &files,
-            &diag.to_codespan(file_id),
+    let mut summary = Summary::default();
+    for diag in &report.diagnostics {
+        summary.record(diag);
+    }
+    let mut deps = config
+        .files
+        .iter()
+        .map(|file| file.path.clone())
+        .collect::<BTreeSet<_>>();
+    for program in &programs {
+        deps.extend(program.deps.iter().cloned());
+    }
+    deps.extend(report.assets.iter().cloned());
+
+    match args.format {
+        Format::Text => {
+            for diag in &report.diagnostics {
+                let _ = term::emit(
+                    &mut writer.lock(),
+                    &term_config,
+                    &report.files,
+                    diag,
+                );
+            }
+        }
+        Format::Json => {
+            println!(
+                "{:#}",
+                output::to_json(&report.files, &report.diagnostics)
+            );
+        }
+        Format::Sarif => {
+            println!(
+                "{:#}",
+                output::to_sarif(&report.files, &report.diagnostics)
+            );
+        }
+    }
+    if summary.errors > 0 || summary.warnings > 0 {
+        eprintln!(
+            "{}, {}",
+            plural(summary.errors, "error"),
+            plural(summary.warnings, "warning")
         );
     }
+
+    if summary.failed(args.deny_warnings) {
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if let Some(stamp) = &args.stamp {
+        fs::write(stamp, "")?;
+        if let Some(depfile) = &args.depfile {
+            write_depfile(depfile, stamp, &deps)?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,341 @@
+use std::env;
+use std::ops::Range;
+use std::path::Path;
+
+use arson_parse::reporting as codespan_reporting;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::LabelStyle;
+use codespan_reporting::diagnostic::Severity;
+use codespan_reporting::files::Files;
+use serde_json::json;
+use serde_json::Value;
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Bug => "bug",
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+        Severity::Help => "help",
+    }
+}
+
+fn file_name<'a, F: Files<'a, FileId = usize>>(
+    files: &'a F,
+    id: usize,
+) -> String {
+    files
+        .name(id)
+        .map(|name| name.to_string())
+        .unwrap_or_default()
+}
+
+// one-based line and column of each end of a byte range
+fn position<'a, F: Files<'a, FileId = usize>>(
+    files: &'a F,
+    id: usize,
+    range: &Range<usize>,
+) -> Option<((usize, usize), (usize, usize))> {
+    let start = files.location(id, range.start).ok()?;
+    let end = files.location(id, range.end).ok()?;
+    Some((
+        (start.line_number, start.column_number),
+        (end.line_number, end.column_number),
+    ))
+}
+
+fn json_label<'a, F: Files<'a, FileId = usize>>(
+    files: &'a F,
+    label: &codespan_reporting::diagnostic::Label<usize>,
+) -> Value {
+    let style = match label.style {
+        LabelStyle::Primary => "primary",
+        LabelStyle::Secondary => "secondary",
+    };
+    let mut value = json!({
+        "style": style,
+        "message": label.message,
+        "file": file_name(files, label.file_id),
+        "byte_range": [label.range.start, label.range.end],
+    });
+    if let Some(((line, column), (end_line, end_column))) =
+        position(files, label.file_id, &label.range)
+    {
+        value["start"] = json!({ "line": line, "column": column });
+        value["end"] = json!({ "line": end_line, "column": end_column });
+    }
+    value
+}
+
+/// Serializes diagnostics to a JSON array, one object per diagnostic.
+pub fn to_json<'a, F: Files<'a, FileId = usize>>(
+    files: &'a F,
+    diagnostics: &[Diagnostic<usize>],
+) -> Value {
+    let diagnostics = diagnostics
+        .iter()
+        .map(|diag| {
+            let file = diag
+                .labels
+                .iter()
+                .find(|label| label.style == LabelStyle::Primary)
+                .or(diag.labels.first())
+                .map(|label| file_name(files, label.file_id));
+
+            json!({
+                "file": file,
+                "severity": severity_name(diag.severity),
+                "code": diag.code,
+                "message": diag.message,
+                "labels": diag
+                    .labels
+                    .iter()
+                    .map(|label| json_label(files, label))
+                    .collect::<Vec<_>>(),
+                "notes": diag.notes,
+            })
+        })
+        .collect();
+
+    Value::Array(diagnostics)
+}
+
+// percent-encodes everything but unreserved characters and `/`
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => {
+                encoded.push(byte as char)
+            }
+            b'-' | b'.' | b'_' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+// a file name as a URI relative to the working directory, with `/`
+// separators. files outside it get an absolute `file` URI instead
+fn artifact_uri(name: &str) -> String {
+    let path = Path::new(name);
+    let relative = env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path);
+    let text = relative.to_string_lossy().replace('\\', "/");
+    let mut text = text.as_str();
+    while let Some(rest) = text.strip_prefix("./") {
+        text = rest;
+    }
+
+    if relative.is_absolute() || text.starts_with('/') {
+        let text = text.trim_start_matches('/');
+        format!("file:///{}", percent_encode(text))
+    } else {
+        percent_encode(text)
+    }
+}
+
+// byte offsets aren't given, since they would be into the decoded text
+// rather than the file, which differ for Latin-1 files
+fn sarif_location<'a, F: Files<'a, FileId = usize>>(
+    files: &'a F,
+    label: &codespan_reporting::diagnostic::Label<usize>,
+) -> Value {
+    let uri = artifact_uri(&file_name(files, label.file_id));
+    let mut location = json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": uri },
+            "region": {},
+        },
+    });
+
+    if let Some(((line, column), (end_line, end_column))) =
+        position(files, label.file_id, &label.range)
+    {
+        let region = &mut location["physicalLocation"]["region"];
+        region["startLine"] = json!(line);
+        region["startColumn"] = json!(column);
+        region["endLine"] = json!(end_line);
+        region["endColumn"] = json!(end_column);
+    }
+
+    if !label.message.is_empty() {
+        location["message"] = json!({ "text": label.message });
+    }
+
+    location
+}
+
+/// Serializes diagnostics to a SARIF 2.1.0 log with a single run.
+pub fn to_sarif<'a, F: Files<'a, FileId = usize>>(
+    files: &'a F,
+    diagnostics: &[Diagnostic<usize>],
+) -> Value {
+    let results = diagnostics
+        .iter()
+        .map(|diag| {
+            let level = match diag.severity {
+                Severity::Bug | Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note | Severity::Help => "note",
+            };
+
+            let (primary, secondary): (Vec<_>, Vec<_>) = diag
+                .labels
+                .iter()
+                .partition(|label| label.style == LabelStyle::Primary);
+
+            let mut text = diag.message.clone();
+            for note in &diag.notes {
+                text.push('\n');
+                text.push_str(note);
+            }
+
+            let mut result = json!({
+                "level": level,
+                "message": { "text": text },
+                "locations": primary
+                    .iter()
+                    .map(|label| sarif_location(files, label))
+                    .collect::<Vec<_>>(),
+                "relatedLocations": secondary
+                    .iter()
+                    .map(|label| sarif_location(files, label))
+                    .collect::<Vec<_>>(),
+            });
+            if let Some(code) = &diag.code {
+                result["ruleId"] = json!(code);
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "dtacheck",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/hmxmilohax/tools",
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Label;
+    use codespan_reporting::files::SimpleFiles;
+
+    use super::*;
+
+    // a diagnostic in `b é.dta` pointing back at `a.dta`, after a character
+    // that takes two bytes
+    fn example() -> (SimpleFiles<String, String>, Vec<Diagnostic<usize>>) {
+        let mut files = SimpleFiles::new();
+        let a = files.add("a.dta".to_string(), "(foo)\n".to_string());
+        let b = files.add("dir/b é.dta".to_string(), "(é {foo})\n".to_string());
+        let diag = Diagnostic::warning()
+            .with_message("bad call")
+            .with_code("wrong-arg-kind")
+            .with_labels(vec![
+                Label::primary(b, 5..8).with_message("here"),
+                Label::secondary(a, 1..4),
+            ])
+            .with_notes(vec!["a note".to_string()]);
+        (files, vec![diag])
+    }
+
+    #[test]
+    fn json_has_a_flat_object_per_diagnostic() {
+        let (files, diagnostics) = example();
+        let json = to_json(&files, &diagnostics);
+        assert_eq!(
+            json,
+            json!([{
+                "file": "dir/b é.dta",
+                "severity": "warning",
+                "code": "wrong-arg-kind",
+                "message": "bad call",
+                "labels": [
+                    {
+                        "style": "primary",
+                        "message": "here",
+                        "file": "dir/b é.dta",
+                        "byte_range": [5, 8],
+                        "start": { "line": 1, "column": 5 },
+                        "end": { "line": 1, "column": 8 },
+                    },
+                    {
+                        "style": "secondary",
+                        "message": "",
+                        "file": "a.dta",
+                        "byte_range": [1, 4],
+                        "start": { "line": 1, "column": 2 },
+                        "end": { "line": 1, "column": 5 },
+                    },
+                ],
+                "notes": ["a note"],
+            }])
+        );
+    }
+
+    #[test]
+    fn sarif_results_use_relative_uris_and_positions() {
+        let (files, diagnostics) = example();
+        let sarif = to_sarif(&files, &diagnostics);
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "dtacheck");
+        assert_eq!(run["columnKind"], "unicodeCodePoints");
+
+        let result = &run["results"][0];
+        assert_eq!(result["level"], "warning");
+        assert_eq!(result["ruleId"], "wrong-arg-kind");
+        assert_eq!(result["message"]["text"], "bad call\na note");
+        assert_eq!(
+            result["locations"],
+            json!([{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": "dir/b%20%C3%A9.dta" },
+                    "region": {
+                        "startLine": 1,
+                        "startColumn": 5,
+                        "endLine": 1,
+                        "endColumn": 8,
+                    },
+                },
+                "message": { "text": "here" },
+            }])
+        );
+        let related = &result["relatedLocations"][0]["physicalLocation"];
+        assert_eq!(related["artifactLocation"]["uri"], "a.dta");
+        assert!(result["relatedLocations"][0].get("message").is_none());
+    }
+
+    #[test]
+    fn artifact_uris_are_relative_with_forward_slashes() {
+        assert_eq!(artifact_uri("./dir\\sub/a.dta"), "dir/sub/a.dta");
+        // archive entries hold a `:`, which would otherwise read as a scheme
+        assert_eq!(
+            artifact_uri("main.hdr:config/a.dtb"),
+            "main.hdr%3Aconfig/a.dtb"
+        );
+
+        let cwd = env::current_dir().unwrap();
+        let inside = cwd.join("x y").join("a.dta");
+        assert_eq!(artifact_uri(&inside.display().to_string()), "x%20y/a.dta");
+        let outside = Path::new("/nonexistent root/a.dta");
+        assert_eq!(
+            artifact_uri(&outside.display().to_string()),
+            "file:///nonexistent%20root/a.dta"
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::ArrayExpression;
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use codespan_reporting::diagnostic::Diagnostic;
+
+use crate::linter::DuplicateKey;
+use crate::program::Program;
+
+/// Platform presets and the macro each one defines.
+pub const PLATFORMS: &[(&str, &str)] =
+    &[("xbox", "HX_XBOX"), ("ps3", "HX_PS3"), ("wii", "HX_WII")];
+
+/// One way of resolving the conditionals in a file.
+pub struct Variant {
+    pub name: String,
+    pub defines: HashSet<String>,
+}
+
+struct Resolver<'p, 'a> {
+    program: &'p Program,
+    asts: &'p [&'p [Expression<'a>]],
+    resolved: Vec<Option<Vec<Expression<'a>>>>,
+    defines: HashSet<String>,
+}
+
+impl<'a> Resolver<'_, 'a> {
+    fn array(
+        &mut self,
+        file: usize,
+        array: &ArrayExpression<'a>,
+    ) -> ArrayExpression<'a> {
+        ArrayExpression {
+            exprs: self.nodes(file, &array.exprs),
+            location: array.location.clone(),
+        }
+    }
+
+    // included files are resolved with the macros defined at the point of
+    // their first inclusion, and their own definitions carry on afterwards
+    fn include(&mut self, file: usize, location: &Range<usize>) {
+        let Some(index) = self.program.included(file, location) else {
+            return;
+        };
+        if self.resolved[index].is_some() {
+            return;
+        }
+
+        self.resolved[index] = Some(Vec::new());
+        let asts = self.asts;
+        let resolved = self.nodes(index, asts[index]);
+        self.resolved[index] = Some(resolved);
+    }
+
+    fn nodes(
+        &mut self,
+        file: usize,
+        ast: &[Expression<'a>],
+    ) -> Vec<Expression<'a>> {
+        let mut out = Vec::new();
+
+        for node in ast {
+            let value = match &node.value {
+                ExpressionValue::Array(array) => {
+                    ExpressionValue::Array(self.nodes(file, array))
+                }
+                ExpressionValue::Command(array) => {
+                    ExpressionValue::Command(self.nodes(file, array))
+                }
+                ExpressionValue::Property(array) => {
+                    ExpressionValue::Property(self.nodes(file, array))
+                }
+                ExpressionValue::Define(name, array) => {
+                    self.defines.insert(name.text.to_string());
+                    ExpressionValue::Define(
+                        name.clone(),
+                        self.array(file, array),
+                    )
+                }
+                ExpressionValue::Undefine(name) => {
+                    self.defines.remove(name.text);
+                    node.value.clone()
+                }
+                ExpressionValue::Autorun(array) => {
+                    ExpressionValue::Autorun(self.array(file, array))
+                }
+                ExpressionValue::Include(_)
+                | ExpressionValue::IncludeOptional(_)
+                | ExpressionValue::Merge(_) => {
+                    self.include(file, &node.location);
+                    node.value.clone()
+                }
+                ExpressionValue::Conditional {
+                    is_positive,
+                    symbol,
+                    true_branch,
+                    false_branch,
+                } => {
+                    let defined = self.defines.contains(symbol.text);
+                    let taken = if defined == *is_positive {
+                        Some(true_branch)
+                    } else {
+                        false_branch.as_ref()
+                    };
+                    if let Some(branch) = taken {
+                        out.extend(self.nodes(file, &branch.exprs));
+                    }
+                    continue;
+                }
+                value => value.clone(),
+            };
+
+            out.push(Expression {
+                value,
+                location: node.location.clone(),
+            });
+        }
+
+        out
+    }
+}
+
+/// Replaces every `#ifdef` and `#ifndef` in a program with the branch taken
+/// when the given macros are defined, following `#define`s, `#undef`s and
+/// includes in order. `asts` holds the parsed text of each program file.
+///
+/// Files that are only included from branches that weren't taken are not
+/// part of the resolved program, and come back as `None`.
+pub fn resolve<'a>(
+    program: &Program,
+    asts: &[&[Expression<'a>]],
+    defines: &HashSet<String>,
+) -> Vec<Option<Vec<Expression<'a>>>> {
+    let mut resolver = Resolver {
+        program,
+        asts,
+        resolved: vec![None; asts.len()],
+        defines: defines.clone(),
+    };
+    resolver.resolved[0] = Some(Vec::new());
+    let root = resolver.nodes(0, asts[0]);
+    resolver.resolved[0] = Some(root);
+    resolver.resolved
+}
+
+/// Merges the diagnostics of each variant, noting which variants a
+/// diagnostic applies to when it doesn't apply to all of them.
+pub fn merge_variants(
+    results: Vec<(&str, Vec<Diagnostic<usize>>)>,
+) -> Vec<Diagnostic<usize>> {
+    let total = results.len();
+    let mut merged: Vec<(Diagnostic<usize>, Vec<&str>)> = Vec::new();
+    let mut indices: HashMap<_, usize> = HashMap::new();
+
+    for (name, diagnostics) in results {
+        for diag in diagnostics {
+            match indices.entry(DuplicateKey::new(&diag)) {
+                Entry::Occupied(entry) => merged[*entry.get()].1.push(name),
+                Entry::Vacant(entry) => {
+                    entry.insert(merged.len());
+                    merged.push((diag, vec![name]));
+                }
+            }
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(mut diag, names)| {
+            if names.len() < total {
+                diag.notes.push(format!("only on {}", names.join(", ")));
+            }
+            diag
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use arson_parse::ParseOptions;
+    use codespan_reporting::diagnostic::Label;
+
+    use super::*;
+    use crate::encoding::Decoded;
+
+    fn parse(text: &str) -> Vec<Expression<'_>> {
+        let options = ParseOptions {
+            include_comments: false,
+        };
+        arson_parse::parse_text(text, options).unwrap()
+    }
+
+    // every symbol left in a resolved file, in order
+    fn symbols(ast: &[Expression]) -> Vec<String> {
+        let mut found = Vec::new();
+        for node in ast {
+            match &node.value {
+                ExpressionValue::Symbol(sym) => {
+                    let name: &str = sym.as_ref();
+                    found.push(name.to_string());
+                }
+                ExpressionValue::Array(array) => found.extend(symbols(array)),
+                _ => (),
+            }
+        }
+        found
+    }
+
+    // the symbols of each file in a program resolved for one platform
+    fn resolve_for(
+        program: &Program,
+        platform: &str,
+    ) -> Vec<Option<Vec<String>>> {
+        let texts = program
+            .files
+            .iter()
+            .map(|file| file.text.as_str())
+            .collect::<Vec<_>>();
+        let asts = texts.iter().map(|text| parse(text)).collect::<Vec<_>>();
+        let asts = asts.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let (_, define) = PLATFORMS
+            .iter()
+            .find(|(name, _)| *name == platform)
+            .unwrap();
+        let defines = HashSet::from([define.to_string()]);
+        resolve(program, &asts, &defines)
+            .iter()
+            .map(|ast| ast.as_deref().map(symbols))
+            .collect()
+    }
+
+    fn program(text: &str) -> Program {
+        let decoded = Decoded::from(text.to_string());
+        Program::load("main.dta".into(), None, decoded, None, None)
+    }
+
+    #[test]
+    fn conditionals_take_the_branch_for_each_platform() {
+        let program = program(
+            "#ifdef HX_XBOX\n(xbox)\n#else\n(other)\n#endif\n\
+             #ifndef HX_WII\n(not_wii)\n#endif\n",
+        );
+        let main = |platform| resolve_for(&program, platform).remove(0);
+        let expected = |names: &[&str]| {
+            Some(names.iter().map(|name| name.to_string()).collect())
+        };
+        assert_eq!(main("xbox"), expected(&["xbox", "not_wii"]));
+        assert_eq!(main("ps3"), expected(&["other", "not_wii"]));
+        assert_eq!(main("wii"), expected(&["other"]));
+    }
+
+    #[test]
+    fn defines_and_undefs_apply_in_order() {
+        let program = program(
+            "#ifdef SONG\n(before)\n#endif\n#define SONG (1)\n\
+             #ifdef SONG\n(defined)\n#endif\n#undef SONG\n\
+             #ifdef SONG\n(after)\n#endif\n",
+        );
+        let main = resolve_for(&program, "xbox").remove(0).unwrap();
+        assert_eq!(main, ["defined"]);
+    }
+
+    #[test]
+    fn includes_in_branches_not_taken_are_left_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.dta");
+        let text = "#ifdef HX_PS3\n#include ps3.dta\n#endif\n(main)\n";
+        fs::write(&path, text).unwrap();
+        fs::write(dir.path().join("ps3.dta"), "(ps3)\n").unwrap();
+
+        let decoded = Decoded::from(text.to_string());
+        let program =
+            Program::load("main.dta".into(), Some(path), decoded, None, None);
+        assert_eq!(program.files.len(), 2);
+
+        let xbox = resolve_for(&program, "xbox");
+        assert_eq!(xbox[1], None);
+        let ps3 = resolve_for(&program, "ps3");
+        assert_eq!(ps3[1], Some(vec!["ps3".to_string()]));
+    }
+
+    #[test]
+    fn diagnostics_missing_from_some_variants_say_where_they_apply() {
+        let diag = |message: &str, start| {
+            Diagnostic::warning()
+                .with_message(message)
+                .with_labels(vec![Label::primary(0, start..start + 1)])
+        };
+        let everywhere = diag("everywhere", 0);
+        let some = diag("some", 5);
+        let merged = merge_variants(vec![
+            ("xbox", vec![everywhere.clone(), some.clone()]),
+            ("ps3", vec![everywhere.clone()]),
+            ("wii", vec![some.clone(), everywhere.clone()]),
+        ]);
+
+        let found = merged
+            .iter()
+            .map(|diag| (diag.message.as_str(), diag.notes.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("everywhere", vec![]),
+                ("some", vec!["only on xbox, wii".to_string()]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use arson_parse::ParseOptions;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Label;
+
+use crate::encoding;
+use crate::encoding::Decoded;
+use crate::encoding::Encoding;
+use crate::linter::Lint;
+
+pub struct ProgramFile {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub text: String,
+    pub encoding: Encoding,
+    // the first text in a different encoding from the rest of the file
+    pub mixed: Option<Range<usize>>,
+    // the file each include directive resolved to, keyed by the location of
+    // the directive
+    pub includes: Vec<(Range<usize>, usize)>,
+}
+
+#[derive(Clone)]
+pub enum IncludeLint {
+    Missing(String, Range<usize>, Option<String>),
+    Cycle(String, Range<usize>, Vec<String>),
+}
+
+impl Lint for IncludeLint {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        match self {
+            Self::Missing(target, range, error) => {
+                let diag = Diagnostic::error()
+                    .with_message(format!("could not include `{target}`"));
+                let label = Label::primary(id, range.clone());
+                match error {
+                    Some(error) => diag
+                        .with_labels(vec![label])
+                        .with_notes(vec![error.clone()]),
+                    None => diag.with_labels(vec![
+                        label.with_message("file not found")
+                    ]),
+                }
+            }
+            Self::Cycle(target, range, chain) => Diagnostic::error()
+                .with_message(format!("`{target}` includes itself"))
+                .with_labels(vec![Label::primary(id, range.clone())
+                    .with_message("include cycle")])
+                .with_notes(vec![format!("cycle: {}", chain.join(" -> "))]),
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Missing(..) => "missing-include",
+            Self::Cycle(..) => "include-cycle",
+        }
+    }
+}
+
+/// A file together with everything it includes, directly or indirectly.
+/// The first file is the one the program was loaded from.
+pub struct Program {
+    pub files: Vec<ProgramFile>,
+    // problems following includes, with the index of the file they are in
+    pub lints: Vec<(usize, IncludeLint)>,
+    // every file read or looked for, so creating a missing include is
+    // noticed by build systems
+    pub deps: BTreeSet<PathBuf>,
+    // the directory includes were looked for in, usually the game's root
+    pub root: Option<PathBuf>,
+    // what included files are decoded as, guessed for each file when unset
+    encoding: Option<Encoding>,
+}
+
+struct Directive<'a> {
+    target: &'a str,
+    location: Range<usize>,
+    optional: bool,
+}
+
+fn collect_directives<'a>(
+    directives: &mut Vec<Directive<'a>>,
+    ast: &[Expression<'a>],
+) {
+    for node in ast {
+        match &node.value {
+            ExpressionValue::Array(array)
+            | ExpressionValue::Command(array)
+            | ExpressionValue::Property(array) => {
+                collect_directives(directives, array)
+            }
+            ExpressionValue::Define(_, array)
+            | ExpressionValue::Autorun(array) => {
+                collect_directives(directives, &array.exprs)
+            }
+            ExpressionValue::Conditional {
+                true_branch,
+                false_branch,
+                ..
+            } => {
+                collect_directives(directives, &true_branch.exprs);
+                if let Some(false_branch) = false_branch {
+                    collect_directives(directives, &false_branch.exprs);
+                }
+            }
+            ExpressionValue::Include(path) | ExpressionValue::Merge(path) => {
+                directives.push(Directive {
+                    target: path.text,
+                    location: node.location.clone(),
+                    optional: false,
+                })
+            }
+            ExpressionValue::IncludeOptional(path) => {
+                directives.push(Directive {
+                    target: path.text,
+                    location: node.location.clone(),
+                    optional: true,
+                })
+            }
+            _ => (),
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or(path.to_path_buf())
+}
+
+impl Program {
+    /// Loads a file's includes from disk. Includes are looked for next to
+    /// the including file, then under `root` if one is given. Files without
+    /// a path only find includes under `root`. Includes are decoded with
+    /// `encoding`, or whichever encoding each looks to be in.
+    pub fn load(
+        name: String,
+        path: Option<PathBuf>,
+        decoded: Decoded,
+        root: Option<&Path>,
+        encoding: Option<Encoding>,
+    ) -> Self {
+        let mut program = Self {
+            files: vec![ProgramFile {
+                name,
+                path,
+                text: decoded.text,
+                encoding: decoded.encoding,
+                mixed: decoded.mixed,
+                includes: Vec::new(),
+            }],
+            lints: Vec::new(),
+            deps: BTreeSet::new(),
+            root: root.map(Path::to_path_buf),
+            encoding,
+        };
+        program.follow(0, root, &mut vec![0]);
+        program
+    }
+
+    fn find(&self, path: &Path) -> Option<usize> {
+        let path = canonical(path);
+        self.files.iter().position(|file| {
+            file.path.as_deref().is_some_and(|p| canonical(p) == path)
+        })
+    }
+
+    fn follow(
+        &mut self,
+        file: usize,
+        root: Option<&Path>,
+        stack: &mut Vec<usize>,
+    ) {
+        let dir = self.files[file]
+            .path
+            .as_deref()
+            .map(|path| path.parent().unwrap_or(Path::new("")).to_path_buf());
+        if dir.is_none() && root.is_none() {
+            return;
+        }
+
+        let text = self.files[file].text.clone();
+        let options = ParseOptions {
+            include_comments: false,
+        };
+        // parse errors are reported when the file is linted
+        let Ok(ast) = arson_parse::parse_text(&text, options) else {
+            return;
+        };
+        let mut directives = Vec::new();
+        collect_directives(&mut directives, &ast);
+
+        for directive in directives {
+            let mut candidates = Vec::new();
+            if let Some(dir) = &dir {
+                candidates.push(dir.join(directive.target));
+            }
+            if let Some(root) = root {
+                candidates.push(root.join(directive.target));
+            }
+            let Some(path) = candidates.iter().find(|p| p.is_file()).cloned()
+            else {
+                self.deps.insert(candidates.swap_remove(0));
+                if !directive.optional {
+                    self.lints.push((
+                        file,
+                        IncludeLint::Missing(
+                            directive.target.to_string(),
+                            directive.location,
+                            None,
+                        ),
+                    ));
+                }
+                continue;
+            };
+            self.deps.insert(path.clone());
+
+            let index = match self.find(&path) {
+                Some(index) if stack.contains(&index) => {
+                    let start = stack.iter().position(|&i| i == index);
+                    let mut chain = stack[start.unwrap_or(0)..]
+                        .iter()
+                        .map(|&i| self.files[i].name.clone())
+                        .collect::<Vec<_>>();
+                    chain.push(self.files[index].name.clone());
+                    self.lints.push((
+                        file,
+                        IncludeLint::Cycle(
+                            directive.target.to_string(),
+                            directive.location,
+                            chain,
+                        ),
+                    ));
+                    continue;
+                }
+                Some(index) => index,
+                None => {
+                    let file_contents = match fs::read(&path) {
+                        Ok(file_contents) => file_contents,
+                        Err(e) => {
+                            self.lints.push((
+                                file,
+                                IncludeLint::Missing(
+                                    directive.target.to_string(),
+                                    directive.location,
+                                    Some(e.to_string()),
+                                ),
+                            ));
+                            continue;
+                        }
+                    };
+
+                    let decoded =
+                        encoding::decode(&file_contents, self.encoding);
+                    let index = self.files.len();
+                    self.files.push(ProgramFile {
+                        name: path.display().to_string(),
+                        path: Some(path),
+                        text: decoded.text,
+                        encoding: decoded.encoding,
+                        mixed: decoded.mixed,
+                        includes: Vec::new(),
+                    });
+                    stack.push(index);
+                    self.follow(index, root, stack);
+                    stack.pop();
+                    index
+                }
+            };
+            self.files[file].includes.push((directive.location, index));
+        }
+    }
+
+    /// The file an include directive at `location` in `file` resolved to.
+    pub fn included(
+        &self,
+        file: usize,
+        location: &Range<usize>,
+    ) -> Option<usize> {
+        self.files[file]
+            .includes
+            .iter()
+            .find(|(range, _)| range == location)
+            .map(|(_, index)| *index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    // writes each file under a fresh directory
+    fn tree(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, text) in files {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        dir
+    }
+
+    fn load(dir: &Path, name: &str, root: Option<&Path>) -> Program {
+        let path = dir.join(name);
+        let text = fs::read_to_string(&path).unwrap();
+        Program::load(
+            name.to_string(),
+            Some(path),
+            Decoded::from(text),
+            root,
+            None,
+        )
+    }
+
+    fn lint_ids(program: &Program) -> Vec<(usize, &'static str)> {
+        program
+            .lints
+            .iter()
+            .map(|(file, lint)| (*file, lint.id()))
+            .collect()
+    }
+
+    #[test]
+    fn missing_includes_are_reported() {
+        let text = "#include missing.dta\n#include_opt optional.dta\n";
+        let dir = tree(&[("main.dta", text)]);
+        let program = load(dir.path(), "main.dta", None);
+        assert_eq!(lint_ids(&program), [(0, "missing-include")]);
+        let diag = program.lints[0].1.to_codespan(0);
+        assert_eq!(diag.labels[0].range, 0..20);
+
+        // both are still dependencies, so creating them is noticed
+        let deps = [
+            dir.path().join("missing.dta"),
+            dir.path().join("optional.dta"),
+        ];
+        assert_eq!(program.deps, deps.into_iter().collect());
+    }
+
+    #[test]
+    fn include_cycles_are_reported() {
+        let dir = tree(&[
+            ("a.dta", "#include b.dta\n"),
+            ("b.dta", "#include a.dta\n"),
+        ]);
+        let program = load(dir.path(), "a.dta", None);
+        assert_eq!(program.files.len(), 2);
+        assert_eq!(lint_ids(&program), [(1, "include-cycle")]);
+        let diag = program.lints[0].1.to_codespan(1);
+        let b = dir.path().join("b.dta").display().to_string();
+        assert_eq!(diag.notes, [format!("cycle: a.dta -> {b} -> a.dta")]);
+    }
+
+    #[test]
+    fn includes_are_looked_for_next_to_the_file_then_under_root() {
+        let dir = tree(&[
+            (
+                "songs/main.dta",
+                "#include local.dta\n#include shared.dta\n",
+            ),
+            ("songs/local.dta", "(local)\n"),
+            ("local.dta", "(root)\n"),
+            ("shared.dta", "(shared)\n"),
+        ]);
+        let root = dir.path();
+        let program = load(root, "songs/main.dta", Some(root));
+        assert!(program.lints.is_empty());
+
+        let texts = program
+            .files
+            .iter()
+            .map(|file| file.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts[1..], ["(local)\n", "(shared)\n"]);
+        assert_eq!(program.included(0, &(0..18)), Some(1));
+        assert_eq!(program.included(0, &(19..38)), Some(2));
+
+        let deps = [root.join("songs/local.dta"), root.join("shared.dta")];
+        assert_eq!(program.deps, deps.into_iter().collect());
+    }
+
+    #[test]
+    fn files_without_a_path_only_find_includes_under_root() {
+        let dir = tree(&[("shared.dta", "(shared)\n")]);
+        let text = Decoded::from("#include shared.dta\n".to_string());
+        let unsaved = Program::load("new".into(), None, text, None, None);
+        assert_eq!(unsaved.files.len(), 1);
+        assert!(unsaved.lints.is_empty());
+
+        let text = Decoded::from("#include shared.dta\n".to_string());
+        let program =
+            Program::load("new".into(), None, text, Some(dir.path()), None);
+        assert_eq!(program.files.len(), 2);
+    }
+}
@@ -0,0 +1,237 @@
+use std::ops::Range;
+
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Label;
+
+use crate::linter::Lint;
+use crate::linter::LINT_IDS;
+
+const MARKER: &str = "dtacheck:";
+
+struct Suppression {
+    range: Range<usize>,
+    ids: Vec<String>,
+}
+
+/// Regions of a file where lints have been allowed with a comment such as
+/// `; dtacheck: allow(too-many-args, wrong-arg-kind)`.
+///
+/// A comment sharing its line with code applies to that line. A comment on
+/// a line of its own applies to the whole node that follows it.
+#[derive(Default)]
+pub struct Suppressions(Vec<Suppression>);
+
+struct UnknownLintId(String, Range<usize>);
+
+impl Lint for UnknownLintId {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        Diagnostic::warning()
+            .with_message(format!("unknown lint `{}`", self.0))
+            .with_labels(vec![Label::primary(id, self.1.clone())])
+    }
+
+    fn id(&self) -> &'static str {
+        "unknown-lint"
+    }
+}
+
+// the lint ids listed in a suppression comment, with their offsets in the
+// comment text
+fn parse_comment(text: &str) -> Option<Vec<(&str, usize)>> {
+    let start = text.find(MARKER)? + MARKER.len();
+    let rest = text[start..].trim_start();
+
+    let args = rest.strip_prefix("allow")?.trim_start().strip_prefix('(')?;
+    let args_start = text.len() - args.len();
+    let end = args.find(')')?;
+
+    let mut ids = Vec::new();
+    let mut offset = args_start;
+    for id in args[..end].split(',') {
+        let trimmed = id.trim();
+        if !trimmed.is_empty() {
+            let lead = id.len() - id.trim_start().len();
+            ids.push((trimmed, offset + lead));
+        }
+        offset += id.len() + 1;
+    }
+
+    Some(ids)
+}
+
+fn line_range(text: &str, pos: usize) -> Range<usize> {
+    let start = text[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let end = text[pos..].find('\n').map_or(text.len(), |i| pos + i);
+    start..end
+}
+
+impl Suppressions {
+    /// Collects suppression comments from an AST parsed with comments
+    /// included, returning lints for any unknown lint IDs they name.
+    pub fn from_ast(
+        text: &str,
+        ast: &[Expression],
+    ) -> (Self, Vec<Box<dyn Lint>>) {
+        let mut suppressions = Self::default();
+        let mut lints = Vec::new();
+        suppressions.collect(text, ast, &mut lints);
+        (suppressions, lints)
+    }
+
+    fn collect(
+        &mut self,
+        text: &str,
+        ast: &[Expression],
+        lints: &mut Vec<Box<dyn Lint>>,
+    ) {
+        for (i, node) in ast.iter().enumerate() {
+            match &node.value {
+                ExpressionValue::Array(array)
+                | ExpressionValue::Command(array)
+                | ExpressionValue::Property(array) => {
+                    self.collect(text, array, lints)
+                }
+                ExpressionValue::Define(_, array)
+                | ExpressionValue::Autorun(array) => {
+                    self.collect(text, &array.exprs, lints)
+                }
+                ExpressionValue::Conditional {
+                    true_branch,
+                    false_branch,
+                    ..
+                } => {
+                    self.collect(text, &true_branch.exprs, lints);
+                    if let Some(false_branch) = false_branch {
+                        self.collect(text, &false_branch.exprs, lints);
+                    }
+                }
+                ExpressionValue::Comment(comment) => {
+                    let comment: &str = comment;
+                    let Some(ids) = parse_comment(comment) else {
+                        continue;
+                    };
+
+                    // the comment text may not include the leading `;`s
+                    let comment_start = node.location.end - comment.len();
+                    for (id, offset) in &ids {
+                        if !LINT_IDS.contains(id) {
+                            let start = comment_start + offset;
+                            lints.push(Box::new(UnknownLintId(
+                                id.to_string(),
+                                start..start + id.len(),
+                            )));
+                        }
+                    }
+
+                    let line = line_range(text, node.location.start);
+                    let trailing = !text[line.start..node.location.start]
+                        .trim()
+                        .is_empty();
+                    let range = if trailing {
+                        line
+                    } else {
+                        let next = ast[i + 1..].iter().find(|e| {
+                            !matches!(
+                                e.value,
+                                ExpressionValue::Comment(_)
+                                    | ExpressionValue::BlockComment(_)
+                                    | ExpressionValue::BlankLine
+                            )
+                        });
+                        match next {
+                            Some(next) => next.location.clone(),
+                            None => continue,
+                        }
+                    };
+
+                    self.0.push(Suppression {
+                        range,
+                        ids: ids.iter().map(|(id, _)| id.to_string()).collect(),
+                    });
+                }
+                _ => (),
+            }
+        }
+    }
+
+    pub fn is_suppressed(&self, id: &str, pos: usize) -> bool {
+        self.0.iter().any(|suppression| {
+            suppression.range.contains(&pos)
+                && suppression.ids.iter().any(|i| i == id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arson_parse::ParseOptions;
+
+    use super::*;
+
+    fn suppressions(text: &str) -> (Suppressions, Vec<Box<dyn Lint>>) {
+        let options = ParseOptions {
+            include_comments: true,
+        };
+        let ast = arson_parse::parse_text(text, options).unwrap();
+        Suppressions::from_ast(text, &ast)
+    }
+
+    fn pos(text: &str, needle: &str) -> usize {
+        text.find(needle).unwrap()
+    }
+
+    #[test]
+    fn parses_ids_and_their_offsets() {
+        let comment = "; dtacheck: allow( too-many-args,wrong-arg-kind )";
+        assert_eq!(
+            parse_comment(comment),
+            Some(vec![("too-many-args", 19), ("wrong-arg-kind", 33)])
+        );
+        assert_eq!(parse_comment("; dtacheck: deny(too-many-args)"), None);
+        assert_eq!(parse_comment("; nothing to see"), None);
+    }
+
+    #[test]
+    fn trailing_comment_covers_its_line() {
+        let text = "{a 1 2} ; dtacheck: allow(too-many-args)\n{b 1 2}\n";
+        let (suppressions, _) = suppressions(text);
+
+        assert!(suppressions.is_suppressed("too-many-args", pos(text, "{a")));
+        assert!(!suppressions.is_suppressed("too-many-args", pos(text, "{b")));
+        assert!(!suppressions.is_suppressed("wrong-arg-kind", pos(text, "{a")));
+    }
+
+    #[test]
+    fn own_line_comment_covers_the_next_node() {
+        let text = "; dtacheck: allow(wrong-arg-kind, too-few-args)\n\n\
+                    {a\n   \"x\"}\n{b \"y\"}\n";
+        let (suppressions, _) = suppressions(text);
+
+        assert!(suppressions.is_suppressed("wrong-arg-kind", pos(text, "{a")));
+        assert!(suppressions.is_suppressed("too-few-args", pos(text, "\"x")));
+        assert!(!suppressions.is_suppressed("wrong-arg-kind", pos(text, "{b")));
+    }
+
+    #[test]
+    fn comment_at_the_end_covers_nothing() {
+        let text = "{a 1 2}\n; dtacheck: allow(too-many-args)\n";
+        let (suppressions, _) = suppressions(text);
+
+        assert!(!suppressions.is_suppressed("too-many-args", pos(text, "{a")));
+    }
+
+    #[test]
+    fn unknown_ids_are_reported_where_they_are_written() {
+        let text = "{a 1} ; dtacheck: allow(too-many-args, no-such-lint)\n";
+        let (_, lints) = suppressions(text);
+
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].id(), "unknown-lint");
+        let diag = lints[0].to_codespan(0);
+        let start = pos(text, "no-such-lint");
+        assert_eq!(diag.labels[0].range, start..start + "no-such-lint".len());
+    }
+}
@@ -0,0 +1,435 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::diagnostic::Label;
+
+use crate::linter::Lint;
+
+/// Variables the engine always provides.
+pub const BUILTIN_VARIABLES: &[&str] = &["this"];
+
+// commands that assign to a variable given as their first argument, and
+// whether they also read it
+const ASSIGNMENTS: &[(&str, bool)] = &[
+    ("set", false),
+    ("+=", true),
+    ("-=", true),
+    ("*=", true),
+    ("/=", true),
+    ("%=", true),
+    ("&=", true),
+    ("|=", true),
+    ("^=", true),
+    ("++", true),
+    ("--", true),
+];
+
+fn variable_name<'a>(node: &'a Expression) -> Option<&'a str> {
+    match &node.value {
+        ExpressionValue::Variable(name) => {
+            let name: &str = name;
+            Some(name.trim_start_matches('$'))
+        }
+        _ => None,
+    }
+}
+
+fn symbol_name<'a>(node: &'a Expression) -> Option<&'a str> {
+    match &node.value {
+        ExpressionValue::Symbol(sym) => Some(sym.as_ref()),
+        _ => None,
+    }
+}
+
+enum VariableLint {
+    Undefined(String, Range<usize>),
+    Unused(String, Range<usize>),
+}
+
+impl Lint for VariableLint {
+    fn to_codespan(&self, id: usize) -> Diagnostic<usize> {
+        match self {
+            Self::Undefined(name, range) => Diagnostic::warning()
+                .with_message(format!("`${name}` is read but never set"))
+                .with_labels(vec![Label::primary(id, range.clone())]),
+            Self::Unused(name, range) => Diagnostic::warning()
+                .with_message(format!("`${name}` is set but never read"))
+                .with_labels(vec![Label::primary(id, range.clone())]),
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Undefined(..) => "undefined-variable",
+            Self::Unused(..) => "unused-variable",
+        }
+    }
+}
+
+struct Local {
+    name: String,
+    range: Range<usize>,
+    read: bool,
+    // loop variables and handler arguments are often unused on purpose
+    report_unused: bool,
+}
+
+struct Read {
+    name: String,
+    file_id: usize,
+    range: Range<usize>,
+}
+
+/// Global variable assignments and reads across the files being linted,
+/// along with locals that are never read.
+#[derive(Default)]
+pub struct VariableTable {
+    assigned: HashSet<String>,
+    reads: Vec<Read>,
+    unused: Vec<(usize, VariableLint)>,
+}
+
+struct Walker<'t> {
+    table: &'t mut VariableTable,
+    file_id: usize,
+    scopes: Vec<Vec<Local>>,
+}
+
+impl Walker<'_> {
+    fn local(&mut self, name: &str) -> Option<&mut Local> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|local| local.name == name)
+    }
+
+    fn declare(&mut self, node: &Expression, report_unused: bool) {
+        if let Some(name) = variable_name(node) {
+            self.scopes.last_mut().unwrap().push(Local {
+                name: name.to_string(),
+                range: node.location.clone(),
+                read: false,
+                report_unused,
+            });
+        }
+    }
+
+    fn read(&mut self, name: &str, range: Range<usize>) {
+        match self.local(name) {
+            Some(local) => local.read = true,
+            None => self.table.reads.push(Read {
+                name: name.to_string(),
+                file_id: self.file_id,
+                range,
+            }),
+        }
+    }
+
+    fn assign(&mut self, name: &str, range: Range<usize>, reads: bool) {
+        if reads {
+            self.read(name, range);
+        } else if self.local(name).is_none() {
+            self.table.assigned.insert(name.to_string());
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(Vec::new());
+        f(self);
+        for local in self.scopes.pop().unwrap() {
+            if local.report_unused && !local.read {
+                self.table.unused.push((
+                    self.file_id,
+                    VariableLint::Unused(local.name, local.range),
+                ));
+            }
+        }
+    }
+
+    fn nodes(&mut self, ast: &[Expression]) {
+        for node in ast {
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Expression) {
+        match &node.value {
+            ExpressionValue::Variable(_) => {
+                let name = variable_name(node).unwrap_or_default();
+                self.read(name, node.location.clone());
+            }
+            ExpressionValue::Command(array) => self.command(array),
+            ExpressionValue::Array(array) => self.array(array),
+            ExpressionValue::Property(array) => self.nodes(array),
+            ExpressionValue::Define(_, array)
+            | ExpressionValue::Autorun(array) => self.nodes(&array.exprs),
+            ExpressionValue::Conditional {
+                true_branch,
+                false_branch,
+                ..
+            } => {
+                self.nodes(&true_branch.exprs);
+                if let Some(false_branch) = false_branch {
+                    self.nodes(&false_branch.exprs);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // handlers are written `(name ($arg1 $arg2) {body} ...)`
+    fn array(&mut self, array: &[Expression]) {
+        let args = match array {
+            [head, args, ..] if symbol_name(head).is_some() => {
+                match &args.value {
+                    ExpressionValue::Array(args)
+                        if !args.is_empty()
+                            && args
+                                .iter()
+                                .all(|a| variable_name(a).is_some()) =>
+                    {
+                        Some(args)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        match args {
+            Some(args) => self.scoped(|walker| {
+                for arg in args {
+                    walker.declare(arg, false);
+                }
+                walker.nodes(&array[2..]);
+            }),
+            None => self.nodes(array),
+        }
+    }
+
+    fn command(&mut self, array: &[Expression]) {
+        let Some(head) = array.first().and_then(symbol_name) else {
+            self.nodes(array);
+            return;
+        };
+        let args = &array[1..];
+
+        if let Some((_, reads)) = ASSIGNMENTS.iter().find(|(c, _)| *c == head) {
+            match args.first() {
+                Some(target) if variable_name(target).is_some() => {
+                    let name = variable_name(target).unwrap_or_default();
+                    self.nodes(&args[1..]);
+                    self.assign(name, target.location.clone(), *reads);
+                }
+                _ => self.nodes(args),
+            }
+            return;
+        }
+
+        match (head, args) {
+            // {foreach $var array body...}
+            ("foreach", [var, source, body @ ..])
+                if variable_name(var).is_some() =>
+            {
+                self.node(source);
+                self.scoped(|walker| {
+                    walker.declare(var, false);
+                    walker.nodes(body);
+                });
+            }
+            // {foreach_int $var start end body...}
+            ("foreach_int", [var, start, end, body @ ..])
+                if variable_name(var).is_some() =>
+            {
+                self.node(start);
+                self.node(end);
+                self.scoped(|walker| {
+                    walker.declare(var, false);
+                    walker.nodes(body);
+                });
+            }
+            // {do ($local value) ($local) body...}
+            ("do", _) => self.scoped(|walker| {
+                let mut body = args;
+                while let Some((first, rest)) = body.split_first() {
+                    let ExpressionValue::Array(local) = &first.value else {
+                        break;
+                    };
+                    let Some(var) =
+                        local.first().filter(|v| variable_name(v).is_some())
+                    else {
+                        break;
+                    };
+                    walker.nodes(&local[1..]);
+                    walker.declare(var, true);
+                    body = rest;
+                }
+                walker.nodes(body);
+            }),
+            // {func name ($arg1 $arg2) body...}
+            ("func", [name, params, body @ ..]) => {
+                self.node(name);
+                match &params.value {
+                    ExpressionValue::Array(params) => {
+                        self.scoped(|walker| {
+                            for param in params {
+                                walker.declare(param, false);
+                            }
+                            walker.nodes(body);
+                        });
+                    }
+                    _ => {
+                        self.node(params);
+                        self.nodes(body);
+                    }
+                }
+            }
+            // `{with object body...}` only changes `$this`, which is always
+            // set, so it declares nothing and is walked like anything else
+            _ => self.nodes(args),
+        }
+    }
+}
+
+impl VariableTable {
+    /// Records the variables a file assigns and reads, reporting locals it
+    /// never reads.
+    pub fn add_file(&mut self, file_id: usize, ast: &[Expression]) {
+        let mut walker = Walker {
+            table: self,
+            file_id,
+            scopes: Vec::new(),
+        };
+        walker.nodes(ast);
+    }
+
+    /// Lints for globals read without being set anywhere and for unused
+    /// locals, with the file id each applies to. `external` names globals
+    /// set outside the linted files, such as by the engine.
+    pub fn lints(
+        self,
+        external: &HashSet<String>,
+    ) -> Vec<(usize, Box<dyn Lint>)> {
+        let mut lints: Vec<(usize, Box<dyn Lint>)> = Vec::new();
+
+        for read in self.reads {
+            let name = read.name.as_str();
+            if !self.assigned.contains(name)
+                && !external.contains(name)
+                && !BUILTIN_VARIABLES.contains(&name)
+            {
+                lints.push((
+                    read.file_id,
+                    Box::new(VariableLint::Undefined(read.name, read.range)),
+                ));
+            }
+        }
+
+        for (file_id, lint) in self.unused {
+            lints.push((file_id, Box::new(lint)));
+        }
+
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arson_parse::ParseOptions;
+
+    use super::*;
+
+    // the id and variable name of each lint over a set of files
+    fn lints(files: &[&str], external: &[&str]) -> Vec<(&'static str, String)> {
+        let mut table = VariableTable::default();
+        for (file_id, text) in files.iter().enumerate() {
+            let options = ParseOptions {
+                include_comments: false,
+            };
+            let ast = arson_parse::parse_text(text, options).unwrap();
+            table.add_file(file_id, &ast);
+        }
+        let external = external.iter().map(|name| name.to_string()).collect();
+        table
+            .lints(&external)
+            .into_iter()
+            .map(|(file_id, lint)| {
+                let diag = lint.to_codespan(file_id);
+                let range = diag.labels[0].range.clone();
+                let name = files[file_id][range].trim_start_matches('$');
+                (lint.id(), name.to_string())
+            })
+            .collect()
+    }
+
+    fn found(id: &'static str, name: &str) -> (&'static str, String) {
+        (id, name.to_string())
+    }
+
+    #[test]
+    fn globals_must_be_set_somewhere() {
+        let files = ["{set $score 1}", "{print $score $missing $this $engine}"];
+        assert_eq!(
+            lints(&files, &["engine"]),
+            [found("undefined-variable", "missing")]
+        );
+
+        // compound assignments read the variable too
+        assert_eq!(
+            lints(&["{+= $total 1}"], &[]),
+            [found("undefined-variable", "total")]
+        );
+    }
+
+    #[test]
+    fn loop_variables_are_scoped_to_their_body() {
+        let text = "{foreach $song $songs {print $song}}\n\
+                    {foreach_int $i 0 3 {print}}\n\
+                    {print $song $i}\n{set $songs ()}";
+        assert_eq!(
+            lints(&[text], &[]),
+            [
+                found("undefined-variable", "song"),
+                found("undefined-variable", "i"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unread_do_locals_are_reported() {
+        let text = "{do ($used 1) ($unused) ($late $used) {print $late}}";
+        assert_eq!(lints(&[text], &[]), [found("unused-variable", "unused")]);
+
+        // assigning to a local doesn't make it a global
+        let text =
+            "{do ($local) {set $local 1} {print $local}}\n{print $local}";
+        assert_eq!(lints(&[text], &[]), [found("undefined-variable", "local")]);
+    }
+
+    #[test]
+    fn func_and_handler_params_are_locals() {
+        let text = "{func add ($a $b) {+ $a $b}}\n\
+                    (on_beat ($beat $unused) {print $beat})\n{print $a}";
+        assert_eq!(lints(&[text], &[]), [found("undefined-variable", "a")]);
+    }
+
+    #[test]
+    fn with_is_a_plain_block() {
+        let text = "{with $obj {set $x 1} {print $x $this}}\n{set $obj 1}";
+        assert!(lints(&[text], &[]).is_empty());
+
+        let text = "{with $obj {print $y}}";
+        assert_eq!(
+            lints(&[text], &[]),
+            [
+                found("undefined-variable", "obj"),
+                found("undefined-variable", "y"),
+            ]
+        );
+    }
+}
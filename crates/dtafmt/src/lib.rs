@@ -0,0 +1,420 @@
+use arson_parse::Diagnostic;
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use arson_parse::ParseOptions;
+
+pub struct Options {
+    /// Spaces per indentation level.
+    pub indent: usize,
+    /// Column that arrays are broken across lines to stay within.
+    pub max_width: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            // the width the game itself prints arrays with
+            indent: 3,
+            max_width: 80,
+        }
+    }
+}
+
+pub enum Error {
+    /// The input could not be parsed.
+    Parse(Vec<Diagnostic>),
+    /// The formatted text would parse differently from the input, which is
+    /// a bug in the formatter.
+    Changed,
+}
+
+const PARSE_OPTIONS: ParseOptions = ParseOptions {
+    include_comments: true,
+};
+
+struct Printer<'a> {
+    text: &'a str,
+    options: &'a Options,
+    out: String,
+}
+
+fn is_comment(node: &Expression) -> bool {
+    matches!(
+        node.value,
+        ExpressionValue::Comment(_) | ExpressionValue::BlockComment(_)
+    )
+}
+
+// nodes that can share a line with the opening bracket of a broken array
+fn is_atom(node: &Expression) -> bool {
+    matches!(
+        node.value,
+        ExpressionValue::Integer(_)
+            | ExpressionValue::Float(_)
+            | ExpressionValue::String(_)
+            | ExpressionValue::Symbol(_)
+            | ExpressionValue::Variable(_)
+            | ExpressionValue::Unhandled
+    )
+}
+
+fn brackets<'e, 'a>(
+    node: &'e Expression<'a>,
+) -> Option<(char, &'e [Expression<'a>], char)> {
+    match &node.value {
+        ExpressionValue::Array(array) => Some(('(', array, ')')),
+        ExpressionValue::Command(array) => Some(('{', array, '}')),
+        ExpressionValue::Property(array) => Some(('[', array, ']')),
+        _ => None,
+    }
+}
+
+impl Printer<'_> {
+    fn source(&self, node: &Expression) -> &str {
+        self.text.get(node.location.clone()).unwrap_or_default()
+    }
+
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |i| i + 1);
+        self.out[line_start..].chars().count()
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        let width = depth * self.options.indent;
+        self.out.push_str(&" ".repeat(width));
+    }
+
+    // whether nothing but whitespace separates two offsets
+    fn same_line(&self, from: usize, to: usize) -> bool {
+        !self.text.get(from..to).unwrap_or_default().contains('\n')
+    }
+
+    fn blank_line_between(&self, from: usize, to: usize) -> bool {
+        let gap = self.text.get(from..to).unwrap_or_default();
+        gap.matches('\n').count() > 1
+    }
+
+    // the node on a single line, unless it holds comments or directives,
+    // which need lines of their own
+    fn flat(&self, node: &Expression) -> Option<String> {
+        if is_atom(node) {
+            return Some(self.source(node).to_string());
+        }
+
+        let (open, array, close) = brackets(node)?;
+        let mut out = String::from(open);
+        for (i, child) in array.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            out.push_str(&self.flat(child)?);
+        }
+        out.push(close);
+        Some(out)
+    }
+
+    fn node(&mut self, node: &Expression, depth: usize) {
+        match &node.value {
+            ExpressionValue::Array(_)
+            | ExpressionValue::Command(_)
+            | ExpressionValue::Property(_) => {
+                let (open, array, close) = brackets(node).unwrap();
+                self.array(open, array, close, node.location.start, depth);
+            }
+            ExpressionValue::Define(name, array) => {
+                self.out.push_str("#define ");
+                self.out.push_str(name.text);
+                self.out.push(' ');
+                self.array('(', &array.exprs, ')', array.location.start, depth);
+            }
+            ExpressionValue::Undefine(name) => {
+                self.out.push_str("#undef ");
+                self.out.push_str(name.text);
+            }
+            ExpressionValue::Include(path) => {
+                self.out.push_str("#include ");
+                self.out.push_str(path.text);
+            }
+            ExpressionValue::IncludeOptional(path) => {
+                self.out.push_str("#include_opt ");
+                self.out.push_str(path.text);
+            }
+            ExpressionValue::Merge(path) => {
+                self.out.push_str("#merge ");
+                self.out.push_str(path.text);
+            }
+            ExpressionValue::Autorun(array) => {
+                self.out.push_str("#autorun ");
+                self.array('{', &array.exprs, '}', array.location.start, depth);
+            }
+            // branches stay at the conditional's own depth
+            ExpressionValue::Conditional {
+                is_positive,
+                symbol,
+                true_branch,
+                false_branch,
+            } => {
+                self.out.push_str(if *is_positive {
+                    "#ifdef "
+                } else {
+                    "#ifndef "
+                });
+                self.out.push_str(symbol.text);
+                self.lines(&true_branch.exprs, depth, symbol.location.end);
+                if let Some(false_branch) = false_branch {
+                    self.newline(depth);
+                    self.out.push_str("#else");
+                    self.lines(
+                        &false_branch.exprs,
+                        depth,
+                        false_branch.location.start,
+                    );
+                }
+                self.newline(depth);
+                self.out.push_str("#endif");
+            }
+            ExpressionValue::Comment(_) | ExpressionValue::BlockComment(_) => {
+                let comment = self.source(node).trim_end().to_string();
+                self.out.push_str(&comment);
+            }
+            _ => {
+                let atom = self.source(node).to_string();
+                self.out.push_str(&atom);
+            }
+        }
+    }
+
+    // arrays that fit go on one line. otherwise the leading atoms stay with
+    // the opening bracket and everything after gets a line of its own
+    fn array(
+        &mut self,
+        open: char,
+        array: &[Expression],
+        close: char,
+        start: usize,
+        depth: usize,
+    ) {
+        let node_fits = |printer: &Self, text: &str| {
+            printer.column() + text.chars().count() <= printer.options.max_width
+        };
+
+        let flat = array
+            .iter()
+            .map(|child| self.flat(child))
+            .collect::<Option<Vec<_>>>()
+            .map(|children| format!("{open}{}{close}", children.join(" ")));
+        if let Some(flat) = flat {
+            if node_fits(self, &flat) {
+                self.out.push_str(&flat);
+                return;
+            }
+        }
+
+        self.out.push(open);
+        let mut prev_end = start + 1;
+        let mut rest = array;
+        while let Some((first, tail)) = rest.split_first() {
+            if !is_atom(first) {
+                break;
+            }
+            let atom = self.source(first).to_string();
+            if rest.len() != array.len() {
+                if !node_fits(self, &format!(" {atom}")) {
+                    break;
+                }
+                self.out.push(' ');
+            }
+            self.out.push_str(&atom);
+            prev_end = first.location.end;
+            rest = tail;
+        }
+
+        self.lines(rest, depth + 1, prev_end);
+        self.newline(depth);
+        self.out.push(close);
+    }
+
+    // prints each node on a line of its own, keeping comments that trailed
+    // a line in the source at the end of it and single blank lines between
+    // nodes
+    fn lines(
+        &mut self,
+        nodes: &[Expression],
+        depth: usize,
+        mut prev_end: usize,
+    ) {
+        let mut first = true;
+        for node in nodes {
+            if matches!(node.value, ExpressionValue::BlankLine) {
+                continue;
+            }
+
+            let start = node.location.start;
+            let trailing = is_comment(node)
+                && !self.out.is_empty()
+                && !self.out.ends_with('\n')
+                && self.same_line(prev_end, start);
+            if trailing {
+                self.out.push(' ');
+            } else {
+                if !first && self.blank_line_between(prev_end, start) {
+                    self.out.push('\n');
+                }
+                // only the first node of the file starts without one
+                if !self.out.is_empty() {
+                    self.newline(depth);
+                }
+            }
+
+            self.node(node, depth);
+            prev_end = node.location.end;
+            first = false;
+        }
+    }
+}
+
+fn same_nodes(a: &[Expression], b: &[Expression]) -> bool {
+    let a = a
+        .iter()
+        .filter(|node| !matches!(node.value, ExpressionValue::BlankLine))
+        .collect::<Vec<_>>();
+    let b = b
+        .iter()
+        .filter(|node| !matches!(node.value, ExpressionValue::BlankLine))
+        .collect::<Vec<_>>();
+    a.len() == b.len() && a.iter().zip(&b).all(|(a, b)| same(a, b))
+}
+
+// compares two trees, ignoring locations, blank lines and whitespace at the
+// end of comments
+fn same(a: &Expression, b: &Expression) -> bool {
+    use ExpressionValue as V;
+
+    match (&a.value, &b.value) {
+        (V::Array(a), V::Array(b))
+        | (V::Command(a), V::Command(b))
+        | (V::Property(a), V::Property(b)) => same_nodes(a, b),
+        (V::Define(a_name, a), V::Define(b_name, b)) => {
+            a_name.text == b_name.text && same_nodes(&a.exprs, &b.exprs)
+        }
+        (V::Autorun(a), V::Autorun(b)) => same_nodes(&a.exprs, &b.exprs),
+        (V::Undefine(a), V::Undefine(b))
+        | (V::Include(a), V::Include(b))
+        | (V::IncludeOptional(a), V::IncludeOptional(b))
+        | (V::Merge(a), V::Merge(b)) => a.text == b.text,
+        (
+            V::Conditional {
+                is_positive: a_positive,
+                symbol: a_symbol,
+                true_branch: a_true,
+                false_branch: a_false,
+            },
+            V::Conditional {
+                is_positive: b_positive,
+                symbol: b_symbol,
+                true_branch: b_true,
+                false_branch: b_false,
+            },
+        ) => {
+            a_positive == b_positive
+                && a_symbol.text == b_symbol.text
+                && same_nodes(&a_true.exprs, &b_true.exprs)
+                && match (a_false, b_false) {
+                    (Some(a), Some(b)) => same_nodes(&a.exprs, &b.exprs),
+                    (None, None) => true,
+                    _ => false,
+                }
+        }
+        (V::Comment(a), V::Comment(b)) => a.trim_end() == b.trim_end(),
+        (a, b) => a == b,
+    }
+}
+
+/// Reformats DTA text, keeping its comments. The result is checked to parse
+/// to the same tree as the input.
+pub fn format(text: &str, options: &Options) -> Result<String, Error> {
+    let ast = arson_parse::parse_text(text, PARSE_OPTIONS)
+        .map_err(|error| Error::Parse(error.diagnostics))?;
+
+    let mut printer = Printer {
+        text,
+        options,
+        out: String::new(),
+    };
+    printer.lines(&ast, 0, 0);
+    let mut out = printer.out;
+    if !out.is_empty() {
+        out.push('\n');
+    }
+
+    match arson_parse::parse_text(&out, PARSE_OPTIONS) {
+        Ok(formatted) if same_nodes(&ast, &formatted) => Ok(out),
+        _ => Err(Error::Changed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: &[&str] = &[
+        "(a   1   (b 2)   {foo $x \"s\"})\n",
+        "; header\n(a 1 ; trailing\n  (b 2) /* block */\n  {foo})\n\n\n(c)\n",
+        "#ifdef HX_XBOX\n(c  (d e))\n#else\n(c)\n#endif\n",
+        "#define SONGS ((one) (two))\n(songs SONGS)\n",
+        "(long_name_here (another_long_name 1 2 3 4 5 6 7 8 9 10 11 12 13 14 \
+         15 16 17 18 19 20 21) (x [prop] {cmd {nested 1 2 3 4 5 6 7 8 9}}))\n",
+    ];
+
+    fn format_with(text: &str, options: &Options) -> String {
+        match format(text, options) {
+            Ok(formatted) => formatted,
+            Err(_) => panic!("could not format:\n{text}"),
+        }
+    }
+
+    fn format_default(text: &str) -> String {
+        format_with(text, &Options::default())
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        for sample in SAMPLES {
+            let once = format_default(sample);
+            assert_eq!(format_default(&once), once, "sample:\n{sample}");
+        }
+    }
+
+    #[test]
+    fn idempotent_at_narrow_widths() {
+        let options = Options {
+            indent: 2,
+            max_width: 20,
+        };
+        for sample in SAMPLES {
+            let once = format_with(sample, &options);
+            assert_eq!(format_with(&once, &options), once);
+        }
+    }
+
+    #[test]
+    fn comments_are_kept_in_order() {
+        let text = "; first\n(a 1 ; second\n  /* third */ (b 2))\n; fourth\n";
+        let formatted = format_default(text);
+
+        let mut rest = formatted.as_str();
+        for comment in ["; first", "; second", "/* third */", "; fourth"] {
+            let at = rest.find(comment).unwrap_or_else(|| {
+                panic!("`{comment}` missing or out of order in:\n{formatted}")
+            });
+            rest = &rest[at + comment.len()..];
+        }
+    }
+
+    #[test]
+    fn trailing_comments_stay_on_their_line() {
+        let formatted = format_default("(a   1   ; one\n  (b 2))\n");
+        assert!(formatted.lines().any(|line| line.trim() == "(a 1 ; one"));
+    }
+}
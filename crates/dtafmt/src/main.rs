@@ -0,0 +1,206 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use arson_parse::reporting as codespan_reporting;
+use clap::Parser as ClapParser;
+use codespan_reporting::files::SimpleFile;
+use codespan_reporting::term;
+use codespan_reporting::term::termcolor::ColorChoice;
+use codespan_reporting::term::termcolor::StandardStream;
+use codespan_reporting::term::Chars;
+use dtacheck::encoding;
+use dtacheck::encoding::Decoded;
+use dtacheck::encoding::Encoding;
+use dtafmt::Options;
+
+#[derive(ClapParser)]
+struct Args {
+    /// .dta files or directories containing them. Formats stdin to stdout
+    /// when none are given
+    files: Vec<PathBuf>,
+    /// List files that aren't formatted instead of rewriting them, and fail
+    /// if there are any
+    #[arg(long)]
+    check: bool,
+    /// Spaces per indentation level
+    #[arg(long, default_value_t = Options::default().indent)]
+    indent: usize,
+    /// Column to break arrays across lines at
+    #[arg(long, default_value_t = Options::default().max_width)]
+    max_width: usize,
+    /// Encoding of .dta files, utf-8 or latin-1. Guessed for each file when
+    /// not given
+    #[arg(long)]
+    encoding: Option<Encoding>,
+}
+
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+// decodes one file's text the way dtacheck does, reporting why it can't be
+// formatted when it wouldn't be written back as it was read
+fn decode(
+    name: &str,
+    bytes: &[u8],
+    encoding: Option<Encoding>,
+) -> Option<Decoded> {
+    let decoded = encoding::decode(bytes, encoding);
+    if decoded.encoding == Encoding::Utf8 && decoded.mixed.is_some() {
+        eprintln!("{name}: the file is not valid UTF-8, so it was left alone");
+        return None;
+    }
+    Some(decoded)
+}
+
+// encodes formatted text like the file it came from, keeping its byte
+// order mark
+fn encode(text: &str, encoding: Encoding, original: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if original.starts_with(BOM) {
+        out.extend_from_slice(BOM);
+    }
+    out.extend_from_slice(&encoding::encode(text, encoding));
+    out
+}
+
+fn collect_files(
+    files: &mut Vec<PathBuf>,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        let is_dta = entry
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("dta"));
+        if entry.is_dir() || is_dta {
+            collect_files(files, &entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+// formats one file's text, reporting why it couldn't be
+fn format(name: &str, text: &str, options: &Options) -> Option<String> {
+    match dtafmt::format(text, options) {
+        Ok(formatted) => Some(formatted),
+        Err(dtafmt::Error::Parse(diagnostics)) => {
+            let writer = StandardStream::stderr(ColorChoice::Auto);
+            let config = codespan_reporting::term::Config {
+                chars: Chars::ascii(),
+                ..Default::default()
+            };
+            let file = SimpleFile::new(name, text);
+            for diag in diagnostics {
+                let _ = term::emit(
+                    &mut writer.lock(),
+                    &config,
+                    &file,
+                    &diag.to_codespan(()),
+                );
+            }
+            None
+        }
+        Err(dtafmt::Error::Changed) => {
+            eprintln!(
+                "{name}: formatting would change what the file means, so it \
+                 was left alone"
+            );
+            None
+        }
+    }
+}
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let args = Args::parse();
+    let options = Options {
+        indent: args.indent,
+        max_width: args.max_width,
+    };
+
+    if args.files.is_empty() {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        let Some(decoded) = decode("<stdin>", &bytes, args.encoding) else {
+            return Ok(ExitCode::FAILURE);
+        };
+        let Some(formatted) = format("<stdin>", &decoded.text, &options) else {
+            return Ok(ExitCode::FAILURE);
+        };
+        if args.check {
+            return Ok(if formatted == decoded.text {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            });
+        }
+        io::stdout().write_all(&encode(
+            &formatted,
+            decoded.encoding,
+            &bytes,
+        ))?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut failed = false;
+    let mut files = Vec::new();
+    for path in &args.files {
+        if let Err(e) = collect_files(&mut files, path) {
+            eprintln!("could not read `{}`: {e}", path.display());
+            failed = true;
+        }
+    }
+
+    for path in &files {
+        let name = path.display().to_string();
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("could not read `{name}`: {e}");
+                failed = true;
+                continue;
+            }
+        };
+        // rewriting a file read lossily would corrupt it
+        let Some(decoded) = decode(&name, &bytes, args.encoding) else {
+            failed = true;
+            continue;
+        };
+        let Some(formatted) = format(&name, &decoded.text, &options) else {
+            failed = true;
+            continue;
+        };
+
+        if formatted == decoded.text {
+            continue;
+        }
+        if args.check {
+            println!("{name}");
+            failed = true;
+        } else if let Err(e) =
+            fs::write(path, encode(&formatted, decoded.encoding, &bytes))
+        {
+            eprintln!("could not write `{name}`: {e}");
+            failed = true;
+        }
+    }
+
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,317 @@
+use std::ops::Range;
+
+use arson_parse::Expression;
+use arson_parse::ExpressionValue;
+use dtacheck::calls::handler_name;
+use dtacheck::linter::Function;
+use lsp_types::Position;
+
+/// The byte offset of an LSP position, whose character counts UTF-16 code
+/// units. Positions past the end of a line are clamped to it.
+pub fn offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(end) => line_start += end + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+/// The LSP position of a byte offset.
+pub fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+pub fn range(text: &str, range: &Range<usize>) -> lsp_types::Range {
+    lsp_types::Range {
+        start: position(text, range.start),
+        end: position(text, range.end),
+    }
+}
+
+// the expressions directly inside a node, including both branches of a
+// conditional
+fn children<'e, 'a>(node: &'e Expression<'a>) -> Vec<&'e [Expression<'a>]> {
+    match &node.value {
+        ExpressionValue::Array(array)
+        | ExpressionValue::Command(array)
+        | ExpressionValue::Property(array) => vec![array],
+        ExpressionValue::Define(_, array) | ExpressionValue::Autorun(array) => {
+            vec![&array.exprs]
+        }
+        ExpressionValue::Conditional {
+            true_branch,
+            false_branch,
+            ..
+        } => {
+            let mut branches = vec![true_branch.exprs.as_slice()];
+            if let Some(false_branch) = false_branch {
+                branches.push(&false_branch.exprs);
+            }
+            branches
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// A symbol under the cursor.
+pub struct SymbolAt<'e> {
+    pub name: &'e str,
+    pub range: Range<usize>,
+    // the command the symbol is a word of, and its position in it
+    pub command: Option<(&'e [Expression<'e>], usize)>,
+}
+
+/// The innermost symbol containing `offset`, including one the cursor sits
+/// just after.
+pub fn symbol_at<'e>(
+    ast: &'e [Expression<'e>],
+    offset: usize,
+) -> Option<SymbolAt<'e>> {
+    symbol_in(ast, offset, None)
+}
+
+fn symbol_in<'e>(
+    ast: &'e [Expression<'e>],
+    offset: usize,
+    command: Option<&'e [Expression<'e>]>,
+) -> Option<SymbolAt<'e>> {
+    for (index, node) in ast.iter().enumerate() {
+        let range = &node.location;
+        if offset < range.start || offset > range.end {
+            continue;
+        }
+
+        match &node.value {
+            ExpressionValue::Symbol(sym) => {
+                return Some(SymbolAt {
+                    name: sym.as_ref(),
+                    range: range.clone(),
+                    command: command.map(|command| (command, index)),
+                })
+            }
+            ExpressionValue::Command(array) => {
+                if let Some(found) = symbol_in(array, offset, Some(array)) {
+                    return Some(found);
+                }
+            }
+            ExpressionValue::Define(name, _)
+                if name.location.contains(&offset) =>
+            {
+                return Some(SymbolAt {
+                    name: name.text,
+                    range: name.location.clone(),
+                    command: None,
+                })
+            }
+            _ => {
+                for exprs in children(node) {
+                    if let Some(found) = symbol_in(exprs, offset, None) {
+                        return Some(found);
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// What defines a name in script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Macro,
+    Handler,
+    Function,
+}
+
+pub struct Definition {
+    pub kind: DefinitionKind,
+    pub name: String,
+    pub range: Range<usize>,
+    // the whole definition, for showing on hover
+    pub body: Range<usize>,
+}
+
+/// Every `#define`, handler and `{func ...}` in a file, in every branch of
+/// its conditionals.
+pub fn definitions(ast: &[Expression]) -> Vec<Definition> {
+    let mut found = Vec::new();
+    collect_definitions(ast, &mut found);
+    found
+}
+
+fn collect_definitions(ast: &[Expression], found: &mut Vec<Definition>) {
+    for node in ast {
+        match &node.value {
+            ExpressionValue::Define(name, _) => found.push(Definition {
+                kind: DefinitionKind::Macro,
+                name: name.text.to_string(),
+                range: name.location.clone(),
+                body: node.location.clone(),
+            }),
+            ExpressionValue::Array(array) => {
+                if let Some(name) = handler_name(array) {
+                    found.push(Definition {
+                        kind: DefinitionKind::Handler,
+                        name: name.to_string(),
+                        range: array[0].location.clone(),
+                        body: node.location.clone(),
+                    });
+                }
+            }
+            // {func name ($args) body...}
+            ExpressionValue::Command(array) => {
+                if let [head, name, ..] = array.as_slice() {
+                    match (&head.value, &name.value) {
+                        (
+                            ExpressionValue::Symbol(head),
+                            ExpressionValue::Symbol(name),
+                        ) if head == "func" => found.push(Definition {
+                            kind: DefinitionKind::Function,
+                            name: name.to_string(),
+                            range: array[1].location.clone(),
+                            body: node.location.clone(),
+                        }),
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+        for exprs in children(node) {
+            collect_definitions(exprs, found);
+        }
+    }
+}
+
+/// The command path ending at `index` and its config entry, if every word
+/// up to it is a symbol.
+pub fn lookup<'f>(
+    funcs: &'f Function,
+    command: &[Expression],
+    index: usize,
+) -> Option<(String, &'f Function)> {
+    let mut path = Vec::new();
+    let mut func = funcs;
+    for word in &command[..=index] {
+        let ExpressionValue::Symbol(sym) = &word.value else {
+            return None;
+        };
+        let name: &str = sym.as_ref();
+        func = func.children.get(name)?;
+        path.push(name);
+    }
+    Some((path.join(" "), func))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use arson_parse::ParseOptions;
+    use dtacheck::config::Config;
+
+    use super::*;
+
+    fn parse(text: &str) -> Vec<Expression<'_>> {
+        let options = ParseOptions {
+            include_comments: false,
+        };
+        arson_parse::parse_text(text, options).unwrap()
+    }
+
+    fn at(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    #[test]
+    fn positions_count_utf16_units() {
+        // `é` is two bytes but one unit, `😀` four bytes but two units
+        let text = "(a)\n(é 😀 b)\n";
+        assert_eq!(offset(text, at(0, 1)), 1);
+        assert_eq!(offset(text, at(1, 2)), text.find(' ').unwrap());
+        assert_eq!(offset(text, at(1, 6)), text.find('b').unwrap());
+
+        assert_eq!(position(text, text.find('b').unwrap()), at(1, 6));
+        assert_eq!(position(text, 4), at(1, 0));
+        for offset_in in [0, 2, 4, 7, text.find('b').unwrap()] {
+            assert_eq!(offset(text, position(text, offset_in)), offset_in);
+        }
+    }
+
+    #[test]
+    fn positions_are_clamped() {
+        let text = "(a)\n(b)";
+        assert_eq!(offset(text, at(0, 99)), 3);
+        assert_eq!(offset(text, at(9, 0)), text.len());
+        assert_eq!(position(text, 99), at(1, 3));
+    }
+
+    #[test]
+    fn symbols_are_found_with_their_command() {
+        let text = "(foo {bar baz 1})";
+        let ast = parse(text);
+
+        let found = symbol_at(&ast, text.find("baz").unwrap()).unwrap();
+        assert_eq!(found.name, "baz");
+        assert_eq!(found.range, 10..13);
+        let (command, index) = found.command.unwrap();
+        assert_eq!((command.len(), index), (3, 1));
+
+        // just after a symbol still finds it
+        let found = symbol_at(&ast, 4).unwrap();
+        assert_eq!(found.name, "foo");
+        assert!(found.command.is_none());
+
+        assert!(symbol_at(&ast, text.find('1').unwrap()).is_none());
+    }
+
+    #[test]
+    fn definitions_include_every_kind() {
+        let text = "#define SONG (1)\n(enter {func helper ($a) $a})\n";
+        let found = definitions(&parse(text))
+            .into_iter()
+            .map(|def| (def.kind, def.name))
+            .collect::<Vec<_>>();
+        assert!(
+            found
+                == [
+                    (DefinitionKind::Macro, "SONG".to_string()),
+                    (DefinitionKind::Handler, "enter".to_string()),
+                    (DefinitionKind::Function, "helper".to_string()),
+                ]
+        );
+    }
+
+    #[test]
+    fn lookup_follows_the_command_path() {
+        let config = Config::parse(Path::new("fns"), "set_mode fast : int\n");
+        let text = "{set_mode fast 1}";
+        let ast = parse(text);
+        let ExpressionValue::Command(command) = &ast[0].value else {
+            panic!("expected a command");
+        };
+
+        let (path, func) = lookup(&config.funcs, command, 1).unwrap();
+        assert_eq!(path, "set_mode fast");
+        assert_eq!(func.signature(), "int");
+        assert!(lookup(&config.funcs, command, 2).is_none());
+    }
+}
@@ -0,0 +1,587 @@
+mod analysis;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+use analysis::DefinitionKind;
+use arson_parse::reporting as codespan_reporting;
+use arson_parse::ParseOptions;
+use codespan_reporting::diagnostic::LabelStyle;
+use codespan_reporting::diagnostic::Severity;
+use codespan_reporting::files::SimpleFiles;
+use dtacheck::check::check;
+use dtacheck::check::Report;
+use dtacheck::config::Config;
+use dtacheck::encoding::Decoded;
+use dtacheck::program::Program;
+use lsp_server::Connection;
+use lsp_server::ErrorCode;
+use lsp_server::Message;
+use lsp_server::Notification;
+use lsp_server::Request;
+use lsp_server::Response;
+use lsp_types::notification::DidChangeTextDocument;
+use lsp_types::notification::DidCloseTextDocument;
+use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::DidSaveTextDocument;
+use lsp_types::notification::Notification as _;
+use lsp_types::notification::PublishDiagnostics;
+use lsp_types::notification::ShowMessage;
+use lsp_types::request::Completion;
+use lsp_types::request::GotoDefinition;
+use lsp_types::request::HoverRequest;
+use lsp_types::request::Request as _;
+use lsp_types::CompletionItem;
+use lsp_types::CompletionItemKind;
+use lsp_types::CompletionOptions;
+use lsp_types::CompletionParams;
+use lsp_types::CompletionResponse;
+use lsp_types::DiagnosticRelatedInformation;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::GotoDefinitionParams;
+use lsp_types::GotoDefinitionResponse;
+use lsp_types::Hover;
+use lsp_types::HoverContents;
+use lsp_types::HoverParams;
+use lsp_types::HoverProviderCapability;
+use lsp_types::InitializeParams;
+use lsp_types::Location;
+use lsp_types::MarkupContent;
+use lsp_types::MarkupKind;
+use lsp_types::MessageType;
+use lsp_types::NumberOrString;
+use lsp_types::OneOf;
+use lsp_types::PublishDiagnosticsParams;
+use lsp_types::ServerCapabilities;
+use lsp_types::ShowMessageParams;
+use lsp_types::TextDocumentSyncCapability;
+use lsp_types::TextDocumentSyncKind;
+use lsp_types::TextDocumentSyncOptions;
+use lsp_types::TextDocumentSyncSaveOptions;
+use lsp_types::Url;
+use serde_json::Value;
+
+const PARSE_OPTIONS: ParseOptions = ParseOptions {
+    include_comments: false,
+};
+
+struct Server {
+    connection: Connection,
+    config: Config,
+    // where includes are looked up when they aren't next to the file
+    // including them
+    root: Option<PathBuf>,
+    // the text of every open document, which may not be saved yet
+    documents: HashMap<Url, String>,
+}
+
+// settings come from the client's `initializationOptions`, as
+// `{"config": "path/to/.dtacheckfns", "root": "path/to/_ark"}`. relative
+// paths are resolved against the workspace, and by default the config is
+// `.dtacheckfns` and the root is `_ark` in the workspace
+fn option(
+    options: Option<&Value>,
+    name: &str,
+    workspace: Option<&PathBuf>,
+    default: &str,
+) -> Option<PathBuf> {
+    let path = options
+        .and_then(|options| options.get(name))
+        .and_then(Value::as_str)
+        .map(PathBuf::from);
+    match (path, workspace) {
+        (Some(path), Some(workspace)) => Some(workspace.join(path)),
+        (Some(path), None) => Some(path),
+        (None, Some(workspace)) => {
+            Some(workspace.join(default)).filter(|path| path.exists())
+        }
+        (None, None) => None,
+    }
+}
+
+impl Server {
+    fn new(connection: Connection, params: InitializeParams) -> Self {
+        let workspace = params
+            .workspace_folders
+            .as_ref()
+            .and_then(|folders| folders.first())
+            .and_then(|folder| folder.uri.to_file_path().ok());
+        let options = params.initialization_options.as_ref();
+        let config_path =
+            option(options, "config", workspace.as_ref(), ".dtacheckfns");
+        let root = option(options, "root", workspace.as_ref(), "_ark");
+
+        let mut server = Self {
+            connection,
+            config: Config::default(),
+            root,
+            documents: HashMap::new(),
+        };
+        if let Some(path) = config_path {
+            match Config::load(&path) {
+                Ok(config) => server.config = config,
+                Err(e) => server.show_error(format!(
+                    "could not read `{}`: {e}",
+                    path.display()
+                )),
+            }
+        }
+        server
+    }
+
+    fn show_error(&self, message: String) {
+        let params = ShowMessageParams {
+            typ: MessageType::ERROR,
+            message,
+        };
+        let notification =
+            Notification::new(ShowMessage::METHOD.into(), params);
+        let _ = self
+            .connection
+            .sender
+            .send(Message::Notification(notification));
+    }
+
+    fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        self.publish_config()?;
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.request(request);
+                    self.connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    self.notification(notification)?
+                }
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => {
+                self.handle::<HoverRequest>(request, Self::hover)
+            }
+            Completion::METHOD => {
+                self.handle::<Completion>(request, Self::completion)
+            }
+            GotoDefinition::METHOD => {
+                self.handle::<GotoDefinition>(request, Self::definition)
+            }
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{}`", request.method),
+            ),
+        }
+    }
+
+    fn handle<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(e) => Response::new_err(
+                request.id,
+                ErrorCode::InvalidParams as i32,
+                e.to_string(),
+            ),
+        }
+    }
+
+    fn notification(
+        &mut self,
+        notification: Notification,
+    ) -> Result<(), Box<dyn Error>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = params::<DidOpenTextDocument>(notification)
+                else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+                self.documents.insert(document.uri.clone(), document.text);
+                self.publish(&document.uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params) =
+                    params::<DidChangeTextDocument>(notification)
+                else {
+                    return Ok(());
+                };
+                // documents are synced in full, so the last change has the
+                // whole text
+                if let Some(change) = params.content_changes.into_iter().last()
+                {
+                    let uri = params.text_document.uri;
+                    self.documents.insert(uri.clone(), change.text);
+                    self.publish(&uri)?;
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                // other open documents may include the saved one
+                let uris = self.documents.keys().cloned().collect::<Vec<_>>();
+                for uri in uris {
+                    self.publish(&uri)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = params::<DidCloseTextDocument>(notification)
+                else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish(&uri)?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // the open document and every file it includes, read from disk
+    fn program(&self, uri: &Url) -> Option<Program> {
+        let text = self.documents.get(uri)?;
+        let path = uri.to_file_path().ok();
+        let name = match &path {
+            Some(path) => path.display().to_string(),
+            None => uri.to_string(),
+        };
+        Some(Program::load(
+            name,
+            path,
+            Decoded::from(text.clone()),
+            self.root.as_deref(),
+            None,
+        ))
+    }
+
+    fn send_diagnostics(
+        &self,
+        uri: &Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> Result<(), Box<dyn Error>> {
+        let params = PublishDiagnosticsParams {
+            uri: uri.clone(),
+            diagnostics,
+            version: None,
+        };
+        let notification =
+            Notification::new(PublishDiagnostics::METHOD.into(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    // config problems are published once, under the config files they are
+    // in, since the config is only loaded at startup
+    fn publish_config(&self) -> Result<(), Box<dyn Error>> {
+        let report = check(&self.config, &[], &[]);
+        let uris = self.config_uris();
+        for (&file_id, uri) in &uris {
+            let diagnostics = diagnostics(&report, &uris, file_id);
+            self.send_diagnostics(uri, diagnostics)?;
+        }
+        Ok(())
+    }
+
+    // config files come first in a report, so their file ids are their
+    // indices
+    fn config_uris(&self) -> HashMap<usize, Url> {
+        self.config
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(file_id, file)| {
+                let path = file.path.canonicalize().ok()?;
+                Some((file_id, Url::from_file_path(path).ok()?))
+            })
+            .collect()
+    }
+
+    fn publish(&self, uri: &Url) -> Result<(), Box<dyn Error>> {
+        let mut uris = self.config_uris();
+        // a config file isn't script, and keeps the problems found in it
+        if uris.values().any(|config| config == uri) {
+            return self.publish_config();
+        }
+        let Some(program) = self.program(uri) else {
+            return self.send_diagnostics(uri, Vec::new());
+        };
+        let programs = std::slice::from_ref(&program);
+        let report = check(&self.config, programs, &[]);
+        let file_ids = &report.file_ids[0];
+        for (file, &file_id) in program.files.iter().zip(file_ids) {
+            let file_uri = match &file.path {
+                Some(path) => Url::from_file_path(path).ok(),
+                None => Some(uri.clone()),
+            };
+            if let Some(file_uri) = file_uri {
+                uris.insert(file_id, file_uri);
+            }
+        }
+
+        self.send_diagnostics(uri, diagnostics(&report, &uris, file_ids[0]))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let document = params.text_document_position_params;
+        let text = self.documents.get(&document.text_document.uri)?;
+        let ast = arson_parse::parse_text(text, PARSE_OPTIONS).ok()?;
+        let offset = analysis::offset(text, document.position);
+        let symbol = analysis::symbol_at(&ast, offset)?;
+
+        let func = symbol.command.and_then(|(command, index)| {
+            analysis::lookup(&self.config.funcs, command, index)
+        });
+        let value = match func {
+            Some((path, func)) => {
+                let overloads = if func.overloads.is_empty() {
+                    std::slice::from_ref(func)
+                } else {
+                    &func.overloads
+                };
+                let signatures = overloads
+                    .iter()
+                    .map(|func| format!("{path}: {}", func.signature()))
+                    .collect::<Vec<_>>();
+                format!("```\n{}\n```", signatures.join("\n"))
+            }
+            None => {
+                let program = self.program(&document.text_document.uri)?;
+                let (file, definition) = program
+                    .files
+                    .iter()
+                    .flat_map(|file| {
+                        let ast =
+                            arson_parse::parse_text(&file.text, PARSE_OPTIONS);
+                        ast.map(|ast| analysis::definitions(&ast))
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(move |definition| (file, definition))
+                    })
+                    .find(|(_, definition)| {
+                        definition.kind == DefinitionKind::Macro
+                            && definition.name == symbol.name
+                    })?;
+                format!("```\n{}\n```", &file.text[definition.body])
+            }
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(analysis::range(text, &symbol.range)),
+        })
+    }
+
+    fn completion(
+        &self,
+        params: CompletionParams,
+    ) -> Option<CompletionResponse> {
+        let uri = params.text_document_position.text_document.uri;
+        let mut items = self
+            .config
+            .funcs
+            .children
+            .iter()
+            .map(|(name, func)| CompletionItem {
+                label: name.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(func.signature()),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        items.extend(self.config.macros.iter().map(|name| CompletionItem {
+            label: name.clone(),
+            kind: Some(CompletionItemKind::CONSTANT),
+            ..Default::default()
+        }));
+
+        if let Some(program) = self.program(&uri) {
+            for file in &program.files {
+                let Ok(ast) =
+                    arson_parse::parse_text(&file.text, PARSE_OPTIONS)
+                else {
+                    continue;
+                };
+                for definition in analysis::definitions(&ast) {
+                    let kind = match definition.kind {
+                        DefinitionKind::Macro => CompletionItemKind::CONSTANT,
+                        DefinitionKind::Handler => CompletionItemKind::METHOD,
+                        DefinitionKind::Function => {
+                            CompletionItemKind::FUNCTION
+                        }
+                    };
+                    if !items.iter().any(|item| item.label == definition.name) {
+                        items.push(CompletionItem {
+                            label: definition.name,
+                            kind: Some(kind),
+                            detail: Some(file.name.clone()),
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
+        }
+
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Option<GotoDefinitionResponse> {
+        let document = params.text_document_position_params;
+        let uri = document.text_document.uri;
+        let text = self.documents.get(&uri)?;
+        let ast = arson_parse::parse_text(text, PARSE_OPTIONS).ok()?;
+        let offset = analysis::offset(text, document.position);
+        let name = analysis::symbol_at(&ast, offset)?.name;
+
+        let program = self.program(&uri)?;
+        let mut locations = Vec::new();
+        for file in &program.files {
+            let Ok(ast) = arson_parse::parse_text(&file.text, PARSE_OPTIONS)
+            else {
+                continue;
+            };
+            let file_uri = match &file.path {
+                Some(path) => Url::from_file_path(path).ok(),
+                None => Some(uri.clone()),
+            };
+            let Some(file_uri) = file_uri else {
+                continue;
+            };
+            for definition in analysis::definitions(&ast) {
+                if definition.name == name {
+                    locations.push(Location {
+                        uri: file_uri.clone(),
+                        range: analysis::range(&file.text, &definition.range),
+                    });
+                }
+            }
+        }
+
+        (!locations.is_empty())
+            .then_some(GotoDefinitionResponse::Array(locations))
+    }
+}
+
+// notifications whose params don't parse are logged and ignored, rather
+// than stopping the server
+fn params<N: lsp_types::notification::Notification>(
+    notification: Notification,
+) -> Option<N::Params> {
+    match notification.extract(N::METHOD) {
+        Ok(params) => Some(params),
+        Err(e) => {
+            eprintln!("ignoring bad `{}` notification: {e}", N::METHOD);
+            None
+        }
+    }
+}
+
+// the diagnostics whose primary label is in a file, as LSP diagnostics
+fn diagnostics(
+    report: &Report,
+    uris: &HashMap<usize, Url>,
+    file_id: usize,
+) -> Vec<lsp_types::Diagnostic> {
+    report
+        .diagnostics
+        .iter()
+        .filter_map(|diag| {
+            let primary = diag.labels.iter().find(|label| {
+                label.style == LabelStyle::Primary && label.file_id == file_id
+            })?;
+            let text = source(&report.files, primary.file_id);
+            let related = diag
+                .labels
+                .iter()
+                .filter(|label| label.style == LabelStyle::Secondary)
+                .filter_map(|label| {
+                    let text = source(&report.files, label.file_id);
+                    Some(DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: uris.get(&label.file_id)?.clone(),
+                            range: analysis::range(text, &label.range),
+                        },
+                        message: label.message.clone(),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut message = diag.message.clone();
+            for note in &diag.notes {
+                message.push('\n');
+                message.push_str(note);
+            }
+
+            Some(lsp_types::Diagnostic {
+                range: analysis::range(text, &primary.range),
+                severity: Some(match diag.severity {
+                    Severity::Bug | Severity::Error => {
+                        DiagnosticSeverity::ERROR
+                    }
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                    Severity::Note => DiagnosticSeverity::INFORMATION,
+                    Severity::Help => DiagnosticSeverity::HINT,
+                }),
+                code: diag.code.clone().map(NumberOrString::String),
+                source: Some("dtacheck".to_string()),
+                message,
+                related_information: (!related.is_empty()).then_some(related),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn source(files: &SimpleFiles<String, String>, file_id: usize) -> &str {
+    files
+        .get(file_id)
+        .map(|file| file.source().as_str())
+        .unwrap_or_default()
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        definition_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    let params = connection.initialize(serde_json::to_value(capabilities)?)?;
+    let params = serde_json::from_value(params)?;
+
+    let mut server = Server::new(connection, params);
+    server.run()?;
+
+    // the writer thread only finishes once the connection is dropped
+    drop(server);
+    io_threads.join()?;
+    Ok(())
+}
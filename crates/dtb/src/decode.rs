@@ -0,0 +1,355 @@
+use std::error::Error;
+use std::fmt::Write;
+
+use encoding_rs::WINDOWS_1252;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let Some(bytes) = self.data.get(self.pos..self.pos + len) else {
+            return Err("unexpected end of DTB".into());
+        };
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Box<dyn Error>> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, Box<dyn Error>> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, Box<dyn Error>> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn str(&mut self) -> Result<String, Box<dyn Error>> {
+        let len = self.u32()? as usize;
+        // script text is Windows-1252, the games' font encoding, which
+        // differs from Latin-1 in 0x80-0x9F
+        let bytes = self.bytes(len)?;
+        Ok(WINDOWS_1252
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned())
+    }
+}
+
+struct Decoder<'a> {
+    reader: Reader<'a>,
+    out: String,
+    line: u32,
+}
+
+impl Decoder<'_> {
+    // starts a new token, moving down to the array's source line if it is
+    // ahead of us so that diagnostics land near the original line
+    fn separate(&mut self, line: Option<u32>) {
+        match line {
+            Some(line) if line > self.line => {
+                for _ in self.line..line {
+                    self.out.push('\n');
+                }
+                self.line = line;
+            }
+            _ => {
+                if !self.out.is_empty()
+                    && !self.out.ends_with(['\n', '(', '{', '['])
+                {
+                    self.out.push(' ');
+                }
+            }
+        }
+    }
+
+    fn token(&mut self, token: &str) {
+        self.separate(None);
+        self.out.push_str(token);
+    }
+
+    fn children(&mut self) -> Result<(), Box<dyn Error>> {
+        let count = self.reader.u16()?;
+        let _line = self.reader.i32()?;
+        let _id = self.reader.i32()?;
+        for _ in 0..count {
+            self.node()?;
+        }
+        Ok(())
+    }
+
+    fn array(&mut self, open: char, close: char) -> Result<(), Box<dyn Error>> {
+        let count = self.reader.u16()?;
+        let line = self.reader.i32()?;
+        let _id = self.reader.i32()?;
+
+        self.separate(u32::try_from(line).ok());
+        self.out.push(open);
+        for _ in 0..count {
+            self.node()?;
+        }
+        self.out.push(close);
+        Ok(())
+    }
+
+    fn node(&mut self) -> Result<(), Box<dyn Error>> {
+        let kind = self.reader.u32()?;
+        match kind {
+            0x00 => {
+                let value = self.reader.i32()?;
+                self.token(&value.to_string());
+            }
+            0x01 => {
+                let value = self.reader.f32()?;
+                let Some(value) = format_float(value) else {
+                    return Err(format!(
+                        "float {value} at offset {:#x} can't be written in \
+                         DTA",
+                        self.reader.pos - 4
+                    )
+                    .into());
+                };
+                self.token(&value);
+            }
+            0x02 => {
+                let name = self.reader.str()?;
+                self.token(&format!("${name}"));
+            }
+            0x03..=0x05 => {
+                let sym = self.reader.str()?;
+                self.token(&quote_symbol(&sym));
+            }
+            0x06 => {
+                self.reader.i32()?;
+                self.token("kDataUnhandled");
+            }
+            0x07 => self.directive("#ifdef")?,
+            0x08 => {
+                self.reader.i32()?;
+                self.directive_line("#else");
+            }
+            0x09 => {
+                self.reader.i32()?;
+                self.directive_line("#endif");
+            }
+            0x10 => self.array('(', ')')?,
+            0x11 => self.array('{', '}')?,
+            0x12 | 0x14 => {
+                let text = self.reader.str()?;
+                self.token(&quote_string(&text));
+            }
+            0x13 => self.array('[', ']')?,
+            0x20 => self.directive("#define")?,
+            0x21 => self.directive("#include")?,
+            0x22 => self.directive("#merge")?,
+            0x23 => self.directive("#ifndef")?,
+            0x24 => {
+                self.reader.i32()?;
+                self.directive_line("#autorun");
+            }
+            0x25 => self.directive("#undef")?,
+            _ => {
+                return Err(format!(
+                    "unknown node type {kind:#x} at offset {:#x}",
+                    self.reader.pos - 4
+                )
+                .into())
+            }
+        }
+        Ok(())
+    }
+
+    fn newline(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+            self.line += 1;
+        }
+    }
+
+    // directives have to sit on their own line
+    fn directive_line(&mut self, directive: &str) {
+        self.newline();
+        self.out.push_str(directive);
+        self.newline();
+    }
+
+    fn directive(&mut self, directive: &str) -> Result<(), Box<dyn Error>> {
+        let arg = self.reader.str()?;
+        self.newline();
+        let _ = write!(self.out, "{directive} {arg}");
+        self.newline();
+        Ok(())
+    }
+}
+
+// floats are written in plain decimal, always with a `.` so they read back
+// as floats. DTA has no way to write NaN or infinity
+fn format_float(value: f32) -> Option<String> {
+    if !value.is_finite() {
+        return None;
+    }
+    let mut text = value.to_string();
+    if !text.contains('.') {
+        text.push_str(".0");
+    }
+    Some(text)
+}
+
+fn quote_symbol(sym: &str) -> String {
+    let needs_quotes = sym.is_empty()
+        || sym.parse::<f64>().is_ok()
+        || sym.starts_with(['$', '#'])
+        || sym
+            .contains(|c: char| c.is_whitespace() || "(){}[];\"'".contains(c));
+
+    // quoted symbols take backslash escapes
+    if needs_quotes {
+        format!("'{}'", sym.replace('\\', "\\\\").replace('\'', "\\'"))
+    } else {
+        sym.to_string()
+    }
+}
+
+fn quote_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\\q"))
+}
+
+/// Decodes an unencrypted DTB back into DTA source text.
+///
+/// Arrays are placed on the line they were originally serialized from, so
+/// line numbers in the output match the original script where possible.
+pub fn decode(data: &[u8]) -> Result<String, Box<dyn Error>> {
+    let mut decoder = Decoder {
+        reader: Reader { data, pos: 0 },
+        out: String::new(),
+        line: 1,
+    };
+
+    if decoder.reader.u8()? != 1 {
+        return Err("not a DTB file".into());
+    }
+    decoder.children()?;
+    decoder.newline();
+    Ok(decoder.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array_header(out: &mut Vec<u8>, count: u16, line: i32) {
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&line.to_le_bytes());
+        out.extend_from_slice(&0i32.to_le_bytes());
+    }
+
+    fn node(out: &mut Vec<u8>, kind: u32, value: &[u8]) {
+        out.extend_from_slice(&kind.to_le_bytes());
+        out.extend_from_slice(value);
+    }
+
+    fn text(value: &[u8]) -> Vec<u8> {
+        let mut out = (value.len() as u32).to_le_bytes().to_vec();
+        out.extend_from_slice(value);
+        out
+    }
+
+    #[test]
+    fn decodes_nodes_onto_their_lines() {
+        let mut dtb = vec![1];
+        array_header(&mut dtb, 2, 1);
+        node(&mut dtb, 0x10, &[]);
+        array_header(&mut dtb, 5, 1);
+        node(&mut dtb, 0x05, &text(b"foo"));
+        node(&mut dtb, 0x00, &7i32.to_le_bytes());
+        node(&mut dtb, 0x12, &text(b"say \"hi\""));
+        node(&mut dtb, 0x01, &1.5f32.to_le_bytes());
+        node(&mut dtb, 0x02, &text(b"var"));
+        node(&mut dtb, 0x11, &[]);
+        array_header(&mut dtb, 1, 3);
+        node(&mut dtb, 0x05, &text(b"two words"));
+
+        assert_eq!(
+            decode(&dtb).unwrap(),
+            "(foo 7 \"say \\qhi\\q\" 1.5 $var)\n\n{'two words'}\n"
+        );
+    }
+
+    #[test]
+    fn decodes_directives_on_their_own_lines() {
+        let mut dtb = vec![1];
+        array_header(&mut dtb, 4, 1);
+        node(&mut dtb, 0x07, &text(b"HX_XBOX"));
+        node(&mut dtb, 0x05, &text(b"xbox"));
+        node(&mut dtb, 0x09, &0i32.to_le_bytes());
+        node(&mut dtb, 0x05, &text(b"after"));
+
+        assert_eq!(
+            decode(&dtb).unwrap(),
+            "#ifdef HX_XBOX\nxbox\n#endif\nafter\n"
+        );
+    }
+
+    #[test]
+    fn decodes_strings_as_windows_1252() {
+        let mut dtb = vec![1];
+        array_header(&mut dtb, 1, 1);
+        node(&mut dtb, 0x12, &text(b"\x93caf\xe9\x94"));
+
+        assert_eq!(decode(&dtb).unwrap(), "\"\u{201c}caf\u{e9}\u{201d}\"\n");
+    }
+
+    #[test]
+    fn escapes_quotes_in_symbols() {
+        let mut dtb = vec![1];
+        array_header(&mut dtb, 2, 1);
+        node(&mut dtb, 0x05, &text(b"don't"));
+        node(&mut dtb, 0x05, &text(b"a\\b c"));
+
+        assert_eq!(decode(&dtb).unwrap(), "'don\\'t' 'a\\\\b c'\n");
+    }
+
+    #[test]
+    fn writes_floats_in_plain_decimal() {
+        let mut dtb = vec![1];
+        array_header(&mut dtb, 4, 1);
+        for value in [1e-7f32, 2.0, 1e20, -0.5] {
+            node(&mut dtb, 0x01, &value.to_le_bytes());
+        }
+        assert_eq!(
+            decode(&dtb).unwrap(),
+            "0.0000001 2.0 100000000000000000000.0 -0.5\n"
+        );
+
+        for value in [f32::NAN, f32::INFINITY] {
+            let mut dtb = vec![1];
+            array_header(&mut dtb, 1, 1);
+            node(&mut dtb, 0x01, &value.to_le_bytes());
+            assert!(decode(&dtb).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_and_unknown_data() {
+        assert!(decode(&[2]).is_err());
+        assert!(decode(&[1, 1, 0]).is_err());
+
+        let mut dtb = vec![1];
+        array_header(&mut dtb, 1, 1);
+        node(&mut dtb, 0x30, &[]);
+        assert!(decode(&dtb).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+pub mod crypt;
+pub mod decode;
+
+// node types that can appear in a serialized DataArray
+const KNOWN_NODE_TYPES: &[u32] = &[
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x10, 0x11,
+    0x12, 0x13, 0x14, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25,
+];
+
+/// Checks whether a buffer starts like an unencrypted DTB: a version byte
+/// of 1, the root array header and a known type for its first node.
+pub fn is_dtb(data: &[u8]) -> bool {
+    if data.len() < 11 || data[0] != 1 {
+        return false;
+    }
+
+    let count = u16::from_le_bytes([data[1], data[2]]);
+    if count == 0 {
+        return data.len() == 11;
+    }
+
+    let Some(node_type) = data.get(11..15) else {
+        return false;
+    };
+    let node_type = u32::from_le_bytes(node_type.try_into().unwrap());
+    KNOWN_NODE_TYPES.contains(&node_type)
+}
@@ -0,0 +1,49 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use clap::Parser;
+use dtb::crypt;
+use dtb::crypt::Scheme;
+
+#[derive(clap::Parser)]
+#[command(group(
+    clap::ArgGroup::new("mode")
+        .required(true)
+        .args(["encrypt", "encrypt_old", "decrypt"]),
+))]
+struct Args {
+    /// Encrypt using the new-gen scheme (RB1 and later)
+    #[arg(short = 'e')]
+    encrypt: bool,
+    /// Encrypt using the old-gen scheme (GH1, GH2 and RB1 on PS2)
+    #[arg(short = 'E')]
+    encrypt_old: bool,
+    /// Decrypt, detecting which scheme the input uses
+    #[arg(short = 'd')]
+    decrypt: bool,
+    /// Key to encrypt with
+    #[arg(short, long, default_value_t = crypt::DEFAULT_KEY)]
+    key: u32,
+    input_file: PathBuf,
+    output_file: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let buf = std::fs::read(&args.input_file)?;
+
+    let out = if args.decrypt {
+        match crypt::decrypt_any(&buf) {
+            Some((plain, Some(_))) => plain,
+            Some((_, None)) => return Err("input is not encrypted".into()),
+            None => return Err("input is not a recognized DTB".into()),
+        }
+    } else if args.encrypt_old {
+        crypt::encrypt(&buf, args.key, Scheme::Old)
+    } else {
+        crypt::encrypt(&buf, args.key, Scheme::New)
+    };
+
+    std::fs::write(args.output_file, out)?;
+    Ok(())
+}
@@ -1,5 +1,11 @@
 use std::error::Error;
+use std::fs;
 use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::path::PathBuf;
 
 use crate::fio::read_u32;
 use crate::traits::Load;
@@ -11,8 +17,75 @@ pub enum ArkTypes {
     AmpArk(amp::AmpArchive),
 }
 
+pub struct ArkEntry {
+    pub path: String,
+    pub offset: u64,
+    pub size: u32,
+    pub inflated_size: u32,
+}
+
+impl ArkTypes {
+    pub fn entries(&self) -> Result<Vec<ArkEntry>, Box<dyn Error>> {
+        match self {
+            ArkTypes::AmpArk(amp) => Ok(amp.entries()),
+            ArkTypes::FreqArk(_) => Err("listing files in freq arks is not supported yet".into()),
+        }
+    }
+}
+
+/// The files an archive's data is in, with their sizes. A `.hdr` only holds the header, and the
+/// offsets of its entries run across the `<name>_N.ark` parts next to it in order. Other archives
+/// hold their own data.
+pub struct ArkParts(Vec<(PathBuf, u64)>);
+
+impl ArkParts {
+    pub fn find(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let is_hdr = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("hdr"));
+        if !is_hdr {
+            return Ok(Self(vec![(path.to_path_buf(), fs::metadata(path)?.len())]));
+        }
+
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        // parts are named in the same case as the header
+        let ext = if path.extension().is_some_and(|ext| ext == "HDR") { "ARK" } else { "ark" };
+        let mut parts = Vec::new();
+        loop {
+            let part = path.with_file_name(format!("{stem}_{}.{ext}", parts.len()));
+            let Ok(metadata) = fs::metadata(&part) else {
+                break;
+            };
+            parts.push((part, metadata.len()));
+        }
+        if parts.is_empty() {
+            return Err(format!("could not find {stem}_0.{ext} next to the header").into());
+        }
+        Ok(Self(parts))
+    }
+}
+
+pub fn read_entry(parts: &ArkParts, entry: &ArkEntry) -> Result<Vec<u8>, Box<dyn Error>> {
+    if entry.inflated_size != 0 && entry.inflated_size != entry.size {
+        return Err(format!("{} is compressed, which is not supported yet", entry.path).into());
+    }
+
+    let mut start = 0;
+    for (path, size) in &parts.0 {
+        if entry.offset < start + size {
+            let mut f = File::open(path)?;
+            f.seek(SeekFrom::Start(entry.offset - start))?;
+            let mut buf = vec![0u8; entry.size as usize];
+            f.read_exact(&mut buf)?;
+            return Ok(buf);
+        }
+        start += size;
+    }
+    Err(format!("{} is past the end of the archive", entry.path).into())
+}
+
 pub fn load_ark_file(f: &mut File) -> Result<ArkTypes, Box<dyn Error>> {
     let vercheck = read_u32(f, true)?;
+    // the loaders read the header from the start, version included
+    f.seek(SeekFrom::Start(0))?;
     let ark: ArkTypes;
     match vercheck {
         0x004B5241 => {
@@ -26,8 +99,7 @@ pub fn load_ark_file(f: &mut File) -> Result<ArkTypes, Box<dyn Error>> {
             ark = ArkTypes::AmpArk(amp);
         }
         _ => {
-            println!("unrecognized ark version. if gh1 or later, use the .hdr and not the .ark");
-            return Err::<_, Box<dyn Error>>("unkver".into());
+            return Err(format!("unrecognized ark version {vercheck}. if gh1 or later, use the .hdr and not the .ark").into());
         }
     }
     Ok(ark)
@@ -1,6 +1,8 @@
 use std::fmt::{Formatter, Display};
 use std::error::Error;
+use std::io::Read;
 
+use crate::ark::ArkEntry;
 use crate::traits::Load;
 use crate::fio;
 
@@ -54,6 +56,7 @@ pub struct AmpArchive {
     entries: Vec<AmpFileEntry>,
     str_table_size: u32,
     string_table: Vec<String>,
+    string_offsets: Vec<u32>, // byte offset of each string in the table
     string_idx_count: u32,
     string_idx_entries: Vec<u32>
 }
@@ -66,10 +69,33 @@ impl AmpArchive {
             entries: vec![],
             str_table_size: 0,
             string_table: vec![],
+            string_offsets: vec![],
             string_idx_count: 0,
             string_idx_entries: vec![]
         }
     }
+
+    pub fn entries(&self) -> Vec<ArkEntry> {
+        // names index the string index table, which holds byte offsets into the string table
+        let lookup = |idx: u32| {
+            let offset = self.string_idx_entries.get(idx as usize);
+            let pos = offset.and_then(|offset| self.string_offsets.iter().position(|o| o == offset));
+            pos.map(|pos| self.string_table[pos].as_str()).unwrap_or_default()
+        };
+        self.entries.iter().map(|ent| {
+            let name = lookup(ent.file_name_idx);
+            let path = match lookup(ent.folder_name_idx) {
+                "" | "." => name.to_string(),
+                folder => format!("{folder}/{name}"),
+            };
+            ArkEntry {
+                path,
+                offset: ent.offset as u64,
+                size: ent.size,
+                inflated_size: ent.inflated_size,
+            }
+        }).collect()
+    }
 }
 
 impl Load for AmpArchive {
@@ -82,9 +108,15 @@ impl Load for AmpArchive {
             self.entries.push(ent);
         }
         self.str_table_size = fio::read_u32(f, true)?;
-        for _ in 0..self.str_table_size {
-            let st = fio::readstr(f)?;
-            self.string_table.push(st);
+        // the size is in bytes, not strings, and each string runs to its NUL however long it is
+        let mut table = vec![0u8; self.str_table_size as usize];
+        f.read_exact(&mut table)?;
+        let mut offset = 0;
+        while offset < table.len() {
+            let end = table[offset..].iter().position(|&b| b == 0).map_or(table.len(), |len| offset + len);
+            self.string_offsets.push(offset as u32);
+            self.string_table.push(table[offset..end].iter().map(|&b| b as char).collect());
+            offset = end + 1;
         }
         self.string_idx_count = fio::read_u32(f, true)?;
         for _ in 0..self.string_idx_count {
@@ -104,8 +136,8 @@ impl Display for AmpArchive {
         }
         fmt.write_str("END ENTRIES")?;
         fmt.write_fmt(format_args!("String table size: {}", self.str_table_size))?;
-        for i in 0..self.str_table_size {
-            fmt.write_fmt(format_args!("Entry {i}: {}", self.string_table[i as usize]))?;
+        for (i, st) in self.string_table.iter().enumerate() {
+            fmt.write_fmt(format_args!("Entry {i}: {}", st))?;
         }
         fmt.write_fmt(format_args!("String index count: {}", self.string_idx_count))?;
         for i in 0..self.string_idx_entries.len() {
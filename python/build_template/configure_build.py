@@ -97,6 +97,7 @@ print(f"Platform: {args.platform}")
 
 # configure tools
 ark_dir = Path("obj", args.platform, "ark")
+dtacheck_available = True
 match sys.platform:
     case "win32":
         ninja.variable("silence", ">nul")
@@ -118,7 +119,7 @@ match sys.platform:
         ninja.variable("arkhelper", "dependencies/macos/arkhelper")
         ninja.variable("dtab", "dependencies/macos/dtab")
         # dtacheck needs to be compiled for mac
-        ninja.variable("dtacheck", "true")
+        dtacheck_available = False
     case "linux":
         ninja.variable("silence", "> /dev/null")
         ninja.rule("copy", "cp --reflink=auto $in $out",description="COPY $in")
@@ -181,7 +182,16 @@ ninja.rule(
     f"$superfreq png2tex -l error $miloVersion --platform $platform $in $out",
     description="SFREQ $in"
     )
-ninja.rule("dtacheck", "$dtacheck $in .dtacheckfns", description="DTACHECK $in")
+if dtacheck_available:
+    ninja.rule(
+        "dtacheck",
+        "$dtacheck $in .dtacheckfns --root _ark --stamp $out --depfile $out.d",
+        description="DTACHECK $in",
+        depfile="$out.d",
+        deps="gcc",
+    )
+else:
+    ninja.rule("dtacheck", "touch $out", description="DTACHECK $in (skipped)")
 ninja.rule("dtab_serialize", "$dtab -b $in $out", description="DTAB SER $in")
 ninja.rule("dtab_encrypt", f"$dtab $dtb_encrypt $in $out", description="DTAB ENC $in")
 ninja.build("_always", "phony")
@@ -275,11 +285,16 @@ for f in filter(ark_file_filter, Path("_ark").rglob("*")):
             encryption_output = output_directory.joinpath(target_filename)
             stamp = serialize_directory.joinpath(stamp_filename)
             ninja.build(str(stamp), "dtacheck", str(f))
+            # the stamp's depfile tracks includes, without it we have to
+            # reserialize every time
+            serialize_implicit = [str(stamp)]
+            if not dtacheck_available:
+                serialize_implicit.append("_always")
             ninja.build(
                 str(serialize_output),
                 "dtab_serialize",
                 str(f),
-                implicit=[str(stamp), "_always"],
+                implicit=serialize_implicit,
             )
             ninja.build(str(encryption_output), "dtab_encrypt", str(serialize_output))
             ark_files.append(str(encryption_output))